@@ -31,12 +31,41 @@ impl StorageTransition {
         result
     }
 
+    // Canonical order of a batch: sorted by (address, key), every slot once with its last value.
+    pub fn canonicalize(transitions: Vec<Self>) -> Vec<Self> {
+        let mut result: Vec<Self> = Vec::with_capacity(transitions.len());
+        for transition in transitions {
+            match result.binary_search_by(|other| other.slot().cmp(&transition.slot())) {
+                Ok(index) => result[index] = transition,
+                Err(index) => result.insert(index, transition),
+            }
+        }
+        result
+    }
+
+    // Strictly increasing (address, key), see `canonicalize`.
+    pub fn is_canonical(transitions: &[Self]) -> bool {
+        transitions
+            .windows(2)
+            .all(|pair| pair[0].slot() < pair[1].slot())
+    }
+
+    fn slot(&self) -> ([u8; ADDRESS_SIZE], [u8; STORAGE_KEY_OR_VALUE_SIZE]) {
+        (self.address, self.key)
+    }
+
     pub fn compress(transitions: Vec<Self>) -> Vec<u8> {
         let mut result = Vec::new();
+        let mut previous_address = None;
         for transition in transitions {
-            // address
-            result.push(1);
-            result.extend(transition.address);
+            // address, tag 3 is the address of the previous transition
+            if previous_address == Some(transition.address) {
+                result.push(3);
+            } else {
+                result.push(1);
+                result.extend(transition.address);
+            }
+            previous_address = Some(transition.address);
 
             // key
             let mut key = compress_leading_zeroes(transition.key);
@@ -61,6 +90,21 @@ impl StorageTransition {
     }
 
     pub fn decompress(data: Vec<u8>) -> Vec<StorageTransition> {
+        Self::try_decompress(&data).expect("Invalid data")
+    }
+
+    // Same as `decompress`, but `None` if the data is not a valid encoding of whole transitions.
+    pub fn try_decompress(data: &[u8]) -> Option<Vec<StorageTransition>> {
+        Self::decode(data, true)
+    }
+
+    // Same as `try_decompress` for the format the circuit decodes, which has no tag 2 keys
+    // (a preimage and an offset): the circuit would need keccak for them.
+    pub fn try_decompress_without_preimages(data: &[u8]) -> Option<Vec<StorageTransition>> {
+        Self::decode(data, false)
+    }
+
+    fn decode(data: &[u8], preimages: bool) -> Option<Vec<StorageTransition>> {
         let mut result: Vec<StorageTransition> = Vec::new();
 
         let mut ptr = 0;
@@ -69,27 +113,29 @@ impl StorageTransition {
 
         while ptr < data.len() {
             let value = if data[ptr] == 0 {
-                let mut value = [0; STORAGE_KEY_OR_VALUE_SIZE];
-                for index in ptr + 1..=ptr + STORAGE_KEY_OR_VALUE_SIZE {
-                    value[index - ptr - 1] = data[index];
-                }
+                let value = data.get(ptr + 1..=ptr + STORAGE_KEY_OR_VALUE_SIZE)?;
                 ptr += STORAGE_KEY_OR_VALUE_SIZE + 1;
-                ItemSizeType::KEY(value)
+                ItemSizeType::KEY(value.try_into().unwrap())
             } else if data[ptr] == 1 {
-                let mut value = [0; ADDRESS_SIZE];
-                for index in ptr + 1..=ptr + ADDRESS_SIZE {
-                    value[index - ptr - 1] = data[index];
-                }
+                let value = data.get(ptr + 1..=ptr + ADDRESS_SIZE)?;
                 ptr += ADDRESS_SIZE + 1;
-                ItemSizeType::ADDRESS(value)
-            } else if data[ptr] == 2 {
-                let (preimage, offset) = utils::decompress_leading_zeroes(&data[ptr + 1..]);
+                ItemSizeType::ADDRESS(value.try_into().unwrap())
+            } else if data[ptr] == 3 {
+                // the first transition has no previous address
+                let previous = result.len().checked_sub(2)?;
+                ptr += 1;
+                ItemSizeType::ADDRESS(result[previous].address)
+            } else if data[ptr] == 2 && preimages {
+                let (preimage, offset) = utils::decompress_leading_zeroes(&data[ptr + 1..])?;
                 ptr += 1 + offset as usize;
-                let (image_offset, offset) = utils::decompress_leading_zeroes(&data[ptr..]);
+                let (image_offset, offset) = utils::decompress_leading_zeroes(&data[ptr..])?;
                 ptr += offset as usize;
-                ItemSizeType::KEY(utils::slot_from_preimage_and_offset(preimage, image_offset))
+                ItemSizeType::KEY(utils::slot_from_preimage_and_offset(
+                    preimage,
+                    image_offset,
+                )?)
             } else {
-                let (value, offset) = decompress_leading_zeroes(&data[ptr..]);
+                let (value, offset) = decompress_leading_zeroes(&data[ptr..])?;
                 ptr += offset as usize;
                 ItemSizeType::KEY(value)
             };
@@ -108,11 +154,16 @@ impl StorageTransition {
                     expected_field = EncodeItemType::ADDRESS;
                     result.push(Self::default());
                 }
-                _ => panic!("Invalid data"),
+                _ => return None,
             }
         }
 
+        // the data ends in the middle of a transition
+        if !matches!(expected_field, EncodeItemType::ADDRESS) {
+            return None;
+        }
+
         result.pop().unwrap();
-        result
+        Some(result)
     }
 }
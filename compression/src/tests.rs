@@ -38,7 +38,7 @@ fn test_effectiency_simple_contract() {
 
     let optimise = compressed.len() as f64;
     println!("Compressed data len is bytes: {optimise}");
-    
+
     println!(
         "Optimized {:.2} % for simple contract",
         (start_len - optimise) / start_len * 100.0
@@ -67,7 +67,7 @@ fn effeciency_erc20_contract() {
                 221, 31, 123, 46, 34, 67, 213, 90, 55, 0, 12, 54, 222, 56, 77, 0, 132, 12, 1, 5,
             ],
             key: [
-                31, 8, 32, 23, 2, 65, 222, 1, 34, 0, 6, 0, 234, 0, 0, 243, 0, 0, 22, 0, 0, 0,134,
+                31, 8, 32, 23, 2, 65, 222, 1, 34, 0, 6, 0, 234, 0, 0, 243, 0, 0, 22, 0, 0, 0, 134,
                 0, 122, 65, 33, 0, 4, 0, 4, 11,
             ],
             value: [
@@ -82,10 +82,150 @@ fn effeciency_erc20_contract() {
 
     let optimise = compressed.len() as f64;
     println!("Compressed data len is bytes: {optimise}");
-    
+
     println!(
         "Optimized {:.2} % for ERC20",
         (start_len - optimise) / start_len * 100.0
     );
     assert_eq!(transitions, StorageTransition::decompress(compressed));
 }
+
+#[test]
+fn test_every_leading_zeroes_tag_is_decoded() {
+    for zero_bytes in 1..=STORAGE_KEY_OR_VALUE_SIZE {
+        let mut value = [0u8; STORAGE_KEY_OR_VALUE_SIZE];
+        value[zero_bytes..].fill(7);
+        let transitions = vec![StorageTransition {
+            address: [1; ADDRESS_SIZE],
+            key: value,
+            value,
+        }];
+        let compressed = StorageTransition::compress(transitions.clone());
+        assert_eq!(compressed[1 + ADDRESS_SIZE] as usize, 10 + zero_bytes);
+        assert_eq!(transitions, StorageTransition::decompress(compressed));
+    }
+}
+
+#[test]
+fn test_invalid_data_is_not_decoded() {
+    let transitions = vec![StorageTransition {
+        address: [1; ADDRESS_SIZE],
+        key: [2; STORAGE_KEY_OR_VALUE_SIZE],
+        value: [3; STORAGE_KEY_OR_VALUE_SIZE],
+    }];
+    let compressed = StorageTransition::compress(transitions.clone());
+    assert_eq!(
+        StorageTransition::try_decompress(&compressed),
+        Some(transitions)
+    );
+
+    // truncated value
+    assert_eq!(
+        StorageTransition::try_decompress(&compressed[..compressed.len() - 1]),
+        None
+    );
+    // trailing address without the key and the value
+    let mut trailing = compressed.clone();
+    trailing.push(1);
+    trailing.extend([4; ADDRESS_SIZE]);
+    assert_eq!(StorageTransition::try_decompress(&trailing), None);
+    // tag 10 would be a value without leading zeroes, that is tag 0
+    let mut tag_10 = vec![1];
+    tag_10.extend([4; ADDRESS_SIZE]);
+    tag_10.push(10);
+    tag_10.extend([5; STORAGE_KEY_OR_VALUE_SIZE]);
+    tag_10.extend(&compressed[1 + ADDRESS_SIZE + 1 + STORAGE_KEY_OR_VALUE_SIZE..]);
+    assert_eq!(StorageTransition::try_decompress(&tag_10), None);
+}
+
+#[test]
+fn test_canonical_order() {
+    let transition = |address: u8, key: u8, value: u8| {
+        let mut transition = StorageTransition::default();
+        transition.address[19] = address;
+        transition.key[31] = key;
+        transition.value[31] = value;
+        transition
+    };
+    let transitions = vec![
+        transition(2, 1, 1),
+        transition(1, 2, 2),
+        transition(2, 1, 3),
+        transition(1, 1, 4),
+    ];
+    assert!(!StorageTransition::is_canonical(&transitions));
+
+    let canonical = StorageTransition::canonicalize(transitions);
+    assert_eq!(
+        canonical,
+        vec![
+            transition(1, 1, 4),
+            transition(1, 2, 2),
+            transition(2, 1, 3)
+        ]
+    );
+    assert!(StorageTransition::is_canonical(&canonical));
+}
+
+#[test]
+fn test_repeated_address_is_back_referenced() {
+    let transitions = vec![
+        StorageTransition {
+            address: [1; ADDRESS_SIZE],
+            key: [2; STORAGE_KEY_OR_VALUE_SIZE],
+            value: [3; STORAGE_KEY_OR_VALUE_SIZE],
+        },
+        StorageTransition {
+            address: [1; ADDRESS_SIZE],
+            key: [4; STORAGE_KEY_OR_VALUE_SIZE],
+            value: [5; STORAGE_KEY_OR_VALUE_SIZE],
+        },
+    ];
+    let compressed = StorageTransition::compress(transitions.clone());
+    let second = 1 + ADDRESS_SIZE + 2 * (1 + STORAGE_KEY_OR_VALUE_SIZE);
+    assert_eq!(compressed[second], 3);
+    assert_eq!(
+        compressed.len(),
+        second + 1 + 2 * (1 + STORAGE_KEY_OR_VALUE_SIZE)
+    );
+    assert_eq!(
+        transitions,
+        StorageTransition::decompress(compressed.clone())
+    );
+
+    // the first transition has no previous address
+    assert_eq!(
+        StorageTransition::try_decompress(&compressed[second..]),
+        None
+    );
+}
+
+#[test]
+fn test_preimage_key_is_not_in_the_circuit_format() {
+    let mut preimage = [0; STORAGE_KEY_OR_VALUE_SIZE];
+    preimage[STORAGE_KEY_OR_VALUE_SIZE - 1] = 7;
+    let mut offset = [0; STORAGE_KEY_OR_VALUE_SIZE];
+    offset[STORAGE_KEY_OR_VALUE_SIZE - 1] = 1;
+    let transition = StorageTransition {
+        address: [1; ADDRESS_SIZE],
+        key: utils::slot_from_preimage_and_offset(preimage, offset).unwrap(),
+        value: [3; STORAGE_KEY_OR_VALUE_SIZE],
+    };
+
+    let mut compressed = vec![1];
+    compressed.extend(transition.address);
+    compressed.push(2);
+    compressed.extend(compress_leading_zeroes(preimage));
+    compressed.extend(compress_leading_zeroes(offset));
+    compressed.push(0);
+    compressed.extend(transition.value);
+
+    assert_eq!(
+        StorageTransition::try_decompress(&compressed),
+        Some(vec![transition])
+    );
+    assert_eq!(
+        StorageTransition::try_decompress_without_preimages(&compressed),
+        None
+    );
+}
@@ -35,24 +35,27 @@ pub fn compress_leading_zeroes(value: [u8; STORAGE_KEY_OR_VALUE_SIZE]) -> Vec<u8
 
 ///
 /// Decompress some the `STORAGE_KEY_OR_VALUE_SIZE` size value with leading zeroes.
+/// Returns the value and the encoded length, `None` if the data is not a valid encoding.
 ///
-pub fn decompress_leading_zeroes(data: &[u8]) -> ([u8; STORAGE_KEY_OR_VALUE_SIZE], u8) {
-    let zero_bytes = data[0] - 10;
-
-    assert!(zero_bytes > 10 && zero_bytes as usize <= STORAGE_KEY_OR_VALUE_SIZE);
+pub fn decompress_leading_zeroes(data: &[u8]) -> Option<([u8; STORAGE_KEY_OR_VALUE_SIZE], u8)> {
+    // Tags 11..=42, a value without leading zeroes is encoded with tag 0.
+    let zero_bytes = data.first()?.checked_sub(10)? as usize;
+    if zero_bytes == 0 || zero_bytes > STORAGE_KEY_OR_VALUE_SIZE {
+        return None;
+    }
 
+    let len = STORAGE_KEY_OR_VALUE_SIZE - zero_bytes;
     let mut result = [0u8; STORAGE_KEY_OR_VALUE_SIZE];
-    for index in zero_bytes as usize..STORAGE_KEY_OR_VALUE_SIZE {
-        result[index] = data[index - zero_bytes as usize + 1];
-    }
+    result[zero_bytes..].copy_from_slice(data.get(1..=len)?);
 
-    (result, 1 + STORAGE_KEY_OR_VALUE_SIZE as u8 - zero_bytes)
+    Some((result, 1 + len as u8))
 }
 
+// `None` if the slot overflows 256 bits.
 pub fn slot_from_preimage_and_offset(
     preimage: [u8; STORAGE_KEY_OR_VALUE_SIZE],
     offset: [u8; STORAGE_KEY_OR_VALUE_SIZE],
-) -> [u8; STORAGE_KEY_OR_VALUE_SIZE] {
+) -> Option<[u8; STORAGE_KEY_OR_VALUE_SIZE]> {
     let image = sha3::Keccak256::digest(preimage.as_slice())
         .as_slice()
         .to_vec();
@@ -72,6 +75,5 @@ pub fn slot_from_preimage_and_offset(
             ptr -= 1;
         }
     }
-    assert_eq!(add, 0);
-    result
+    (add == 0).then_some(result)
 }
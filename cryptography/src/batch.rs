@@ -0,0 +1,115 @@
+use compression::{sha3, sha3::Digest};
+use franklin_crypto::bellman::{
+    compact_bn256::{Bn256, Fr, G1Affine, G1},
+    plonk::{
+        better_better_cs::{proof::Proof, setup::VerificationKey, verifier},
+        commitments::transcript::{keccak_transcript::RollingKeccakTranscript, Transcript},
+    },
+    CurveAffine, CurveProjective, Engine, Field, PrimeField, PrimeFieldRepr,
+};
+
+use crate::{error::Error, main_circuit::CompressionCircuit, utils::pack_bytes_to_field_elements};
+
+// Verify many proofs against one key, see `verify`.
+// Returns the indices of the invalid proofs, empty if all of them are valid.
+pub fn verify_batch(
+    vk: &VerificationKey<Bn256, CompressionCircuit<Bn256>>,
+    proofs: &[(&Proof<Bn256, CompressionCircuit<Bn256>>, &[Fr])],
+) -> Result<Vec<usize>, Error> {
+    verify_batch_with_transcript::<RollingKeccakTranscript<Fr>>(vk, proofs)
+}
+
+// Every proof is checked up to the final pairing, which is expensive:
+//
+//     e(P_i, [1]_2) * e(Q_i, [x]_2) == 1
+//
+// All of them are combined into one with random coefficients r_i:
+//
+//     e(sum r_i * P_i, [1]_2) * e(sum r_i * Q_i, [x]_2) == 1
+//
+// The coefficients are the hashes of all the pairs, so a prover can't pick the proofs after them.
+// If the combined check fails, the pairings are checked one by one to find the invalid proofs.
+pub fn verify_batch_with_transcript<T: Transcript<Fr>>(
+    vk: &VerificationKey<Bn256, CompressionCircuit<Bn256>>,
+    proofs: &[(&Proof<Bn256, CompressionCircuit<Bn256>>, &[Fr])],
+) -> Result<Vec<usize>, Error> {
+    let mut failed = Vec::new();
+    let mut pairs = Vec::with_capacity(proofs.len());
+    for (index, (proof, public_inputs)) in proofs.iter().enumerate() {
+        if proof.inputs != *public_inputs {
+            failed.push(index);
+            continue;
+        }
+
+        let (valid, pair) =
+            verifier::aggregate::<Bn256, CompressionCircuit<Bn256>, T>(vk, proof, None)?;
+        if valid {
+            pairs.push((index, pair));
+        } else {
+            failed.push(index);
+        }
+    }
+
+    if pairs.is_empty() {
+        return Ok(failed);
+    }
+
+    let mut for_generator = G1::zero();
+    let mut for_x = G1::zero();
+    for ((_, [p, q]), r) in pairs.iter().zip(batch_coefficients(&pairs)) {
+        for_generator.add_assign(&p.mul(r.into_repr()));
+        for_x.add_assign(&q.mul(r.into_repr()));
+    }
+    if pairing_check(vk, for_generator.into_affine(), for_x.into_affine()) {
+        return Ok(failed);
+    }
+
+    for (index, [p, q]) in pairs {
+        if !pairing_check(vk, p, q) {
+            failed.push(index);
+        }
+    }
+    failed.sort_unstable();
+
+    Ok(failed)
+}
+
+// r_0 = 1, the rest are 31 bytes of Keccak256 of all the pairs and the index.
+fn batch_coefficients(pairs: &[(usize, [G1Affine; 2])]) -> Vec<Fr> {
+    let mut seed = sha3::Keccak256::new();
+    for (_, points) in pairs {
+        for point in points {
+            let (x, y) = point.into_xy_unchecked();
+            for coordinate in [x, y] {
+                let mut bytes = Vec::with_capacity(32);
+                coordinate.into_repr().write_be(&mut bytes).unwrap();
+                seed.update(bytes);
+            }
+        }
+    }
+    let seed = seed.finalize();
+
+    (0..pairs.len())
+        .map(|index| {
+            if index == 0 {
+                return Fr::one();
+            }
+            let mut hasher = sha3::Keccak256::new();
+            hasher.update(seed);
+            hasher.update((index as u64).to_be_bytes());
+            pack_bytes_to_field_elements::<Bn256>(&hasher.finalize()[1..])[0]
+        })
+        .collect()
+}
+
+fn pairing_check(
+    vk: &VerificationKey<Bn256, CompressionCircuit<Bn256>>,
+    for_generator: G1Affine,
+    for_x: G1Affine,
+) -> bool {
+    Bn256::final_exponentiation(&Bn256::miller_loop(&[
+        (&for_generator.prepare(), &vk.g2_elements[0].prepare()),
+        (&for_x.prepare(), &vk.g2_elements[1].prepare()),
+    ]))
+    .is_some_and(|result| result == <Bn256 as Engine>::Fqk::one())
+}
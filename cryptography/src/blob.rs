@@ -0,0 +1,580 @@
+use compression::{sha3, sha3::Digest};
+use franklin_crypto::{
+    bellman::{
+        plonk::better_better_cs::cs::ConstraintSystem, Engine, Field, PrimeField, SynthesisError,
+    },
+    plonk::circuit::{
+        allocated_num::Num,
+        boolean::{AllocatedBit, Boolean},
+        byte::Byte,
+        linear_combination::LinearCombination,
+    },
+};
+use num_bigint::{BigInt, BigUint};
+
+use crate::main_circuit::{allocate_and_prove_bytes, RangeTable};
+use crate::state::{enforce_fits_bytes, le_bytes_to_num};
+use crate::utils::*;
+
+// BLS12-381 scalar field modulus q, blob polynomials are over this field.
+const BLS_MODULUS: &str =
+    "52435875175126190479447740508185965837690552500527637822603658699938581184513";
+pub const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+// Every field element of the blob holds 31 bytes of the data, its first byte is zero.
+pub const BYTES_PER_FIELD_ELEMENT: usize = 31;
+// In-circuit elements of the BLS field are 4 limbs of 64 bits, see `Element`.
+const LIMB_BITS: usize = 64;
+const LIMBS: usize = 4;
+
+// Evaluation point for the blob with `versioned_hash`: the low 31 bytes of
+// keccak256(versioned_hash || compressed_data_hash), always below q.
+// The point must be derived after the data is fixed, otherwise the prover could pick data
+// which matches the blob only at the point.
+pub fn blob_evaluation_point(versioned_hash: &[u8; 32], compressed_data_hash: &[u8]) -> [u8; 32] {
+    let mut hasher = sha3::Keccak256::new();
+    hasher.update(versioned_hash);
+    hasher.update(compressed_data_hash);
+
+    let mut point = [0u8; 32];
+    point[1..].copy_from_slice(&hasher.finalize()[1..]);
+    point
+}
+
+// Value of the blob polynomial at `point`, both are 32 big-endian bytes, the inputs of the
+// point evaluation precompile. The blob holds `data` by 31 bytes per element and zeroes after it.
+pub fn evaluate_blob(data: &[u8], point: &[u8; 32]) -> [u8; 32] {
+    let modulus = modulus();
+    let z = BigUint::from_bytes_be(point) % &modulus;
+    let elements = blob_elements(data);
+    assert!(
+        elements.len() <= FIELD_ELEMENTS_PER_BLOB,
+        "data doesn't fit into a blob"
+    );
+
+    // p(z) = (z^N - 1) / N * sum f_i * w_i / (z - w_i)
+    let root = root_of_unity(&modulus);
+    let mut sum = BigUint::default();
+    for (index, element) in elements.iter().enumerate() {
+        let domain_element = domain_element(&modulus, &root, index);
+        if z == domain_element {
+            return to_bytes(element);
+        }
+        let denominator = (&z + &modulus - &domain_element) % &modulus;
+        sum += element * &domain_element % &modulus * inverse(&denominator, &modulus);
+    }
+    let order = BigUint::from(FIELD_ELEMENTS_PER_BLOB);
+    let vanishing = (z.modpow(&order, &modulus) + &modulus - 1u32) % &modulus;
+
+    to_bytes(&(sum % &modulus * vanishing % &modulus * inverse(&order, &modulus) % &modulus))
+}
+
+// Public inputs of `CircuitConfig::blob_evaluation`: the high and the low 128 bits of the point,
+// then of the value.
+pub fn blob_public_inputs<E: Engine>(data: &[u8], point: &[u8; 32]) -> Vec<E::Fr> {
+    [*point, evaluate_blob(data, point)]
+        .iter()
+        .flat_map(|bytes| {
+            bytes
+                .chunks(16)
+                .map(|half| pack_bytes_to_field_elements::<E>(half)[0])
+        })
+        .collect()
+}
+
+// Prove that the polynomial of the blob with the compressed data(range checked bytes, zeroes
+// after them) evaluates to the value at the point, by the barycentric formula of
+// `evaluate_blob`. Returns the public inputs, see `blob_public_inputs`.
+// The bytes from `len` on are enforced to be zero, the point and the value to be below q.
+// A point of the domain which holds the data has no inverse in the formula and can't be proven.
+// circuit arithmetic
+pub(crate) fn enforce_blob_evaluation<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    data: &[Byte<E>],
+    len: &Num<E>,
+    point: Option<&[u8; 32]>,
+    range_table: &RangeTable,
+) -> Result<Vec<Num<E>>, SynthesisError> {
+    // is_padding flips to one at `len` and stays, `len` equals at most one position
+    let mut is_padding = Num::zero();
+    for (index, byte) in data.iter().enumerate() {
+        let position = Num::Constant(E::Fr::from_str(&format!("{}", index)).unwrap());
+        let is_len = Num::equals(cs, len, &position)?;
+        let mut next = LinearCombination::zero();
+        next.add_assign_number_with_coeff(&is_padding, E::Fr::one());
+        next.add_assign_boolean_with_coeff(&is_len, E::Fr::one());
+        is_padding = next.into_num(cs)?;
+        byte.inner
+            .mul(cs, &is_padding)?
+            .enforce_equal(cs, &Num::zero())?;
+    }
+
+    let mut data = data.to_vec();
+    let elements = data.len().div_ceil(BYTES_PER_FIELD_ELEMENT);
+    if elements > FIELD_ELEMENTS_PER_BLOB {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    data.resize(
+        elements * BYTES_PER_FIELD_ELEMENT,
+        Byte { inner: Num::zero() },
+    );
+
+    let modulus = modulus();
+    let root = root_of_unity(&modulus);
+    let z = Element::alloc_reduced(
+        cs,
+        point.map(|point| BigUint::from_bytes_be(point)),
+        range_table,
+    )?;
+    let z_value = z.value().map(|z| z % &modulus);
+
+    // sum f_i * w_i / (z - w_i), every term is checked as t_i * (z - w_i) == f_i * w_i
+    let mut sum = Element::constant(&BigUint::default());
+    for (index, element) in data.chunks(BYTES_PER_FIELD_ELEMENT).enumerate() {
+        let domain_element = domain_element(&modulus, &root, index);
+        let mut bytes: Vec<Byte<E>> = element.iter().rev().copied().collect();
+        bytes.push(Byte { inner: Num::zero() });
+        let element = Element::from_le_bytes(cs, &bytes)?;
+
+        let denominator = z.add(cs, &Element::constant(&(&modulus - &domain_element)))?;
+        let term_value = element.value().zip(z_value.as_ref()).map(|(element, z)| {
+            let denominator = (z + &modulus - &domain_element) % &modulus;
+            element * &domain_element % &modulus * inverse(&denominator, &modulus) % &modulus
+        });
+        let term = Element::alloc(cs, term_value, LIMBS, range_table)?;
+        let domain_element = Element::constant(&domain_element);
+        enforce_congruent(
+            cs,
+            &[(&term, &denominator)],
+            &[(&element, &domain_element)],
+            range_table,
+        )?;
+
+        sum = sum.add(cs, &term)?;
+    }
+
+    // z^N by squaring
+    let one = Element::constant(&BigUint::from(1u32));
+    let mut power = z.clone();
+    for _ in 0..FIELD_ELEMENTS_PER_BLOB.trailing_zeros() {
+        let square_value = power.value().map(|power| &power * &power % &modulus);
+        let square = Element::alloc(cs, square_value, LIMBS, range_table)?;
+        enforce_congruent(cs, &[(&power, &power)], &[(&square, &one)], range_table)?;
+        power = square;
+    }
+
+    // y * N == (z^N - 1) * sum
+    let vanishing = power.add(cs, &Element::constant(&(&modulus - 1u32)))?;
+    let order = BigUint::from(FIELD_ELEMENTS_PER_BLOB);
+    let y_value = vanishing.value().zip(sum.value()).map(|(vanishing, sum)| {
+        vanishing % &modulus * (sum % &modulus) % &modulus * inverse(&order, &modulus) % &modulus
+    });
+    let y = Element::alloc_reduced(cs, y_value, range_table)?;
+    let order = Element::constant(&order);
+    enforce_congruent(cs, &[(&y, &order)], &[(&vanishing, &sum)], range_table)?;
+
+    // the high and the low 128 bits
+    let limb_shift = biguint_to_fe::<E>(&(BigUint::from(1u32) << LIMB_BITS));
+    let mut inputs = Vec::with_capacity(4);
+    for element in [&z, &y] {
+        for half in element.limbs.chunks(2).rev() {
+            let mut lc = LinearCombination::zero();
+            lc.add_assign_number_with_coeff(&half[0], E::Fr::one());
+            lc.add_assign_number_with_coeff(&half[1], limb_shift);
+            inputs.push(lc.into_num(cs)?);
+        }
+    }
+
+    Ok(inputs)
+}
+
+pub(crate) fn modulus() -> BigUint {
+    BigUint::parse_bytes(BLS_MODULUS.as_bytes(), 10).unwrap()
+}
+
+// Primitive root of unity of the blob domain, 7 is the generator of the multiplicative group.
+fn root_of_unity(modulus: &BigUint) -> BigUint {
+    let exponent = (modulus - 1u32) / FIELD_ELEMENTS_PER_BLOB;
+    BigUint::from(7u32).modpow(&exponent, modulus)
+}
+
+// EIP-4844 orders the domain bit-reversed: the element `index` is at root^reverse_bits(index).
+fn domain_element(modulus: &BigUint, root: &BigUint, index: usize) -> BigUint {
+    let bits = FIELD_ELEMENTS_PER_BLOB.trailing_zeros();
+    let exponent = index.reverse_bits() >> (usize::BITS - bits);
+    root.modpow(&BigUint::from(exponent), modulus)
+}
+
+fn inverse(value: &BigUint, modulus: &BigUint) -> BigUint {
+    value.modpow(&(modulus - 2u32), modulus)
+}
+
+// The data by 31 bytes, the last element is padded with zeroes.
+fn blob_elements(data: &[u8]) -> Vec<BigUint> {
+    data.chunks(BYTES_PER_FIELD_ELEMENT)
+        .map(|chunk| {
+            let mut bytes = chunk.to_vec();
+            bytes.resize(BYTES_PER_FIELD_ELEMENT, 0);
+            BigUint::from_bytes_be(&bytes)
+        })
+        .collect()
+}
+
+fn to_bytes(value: &BigUint) -> [u8; 32] {
+    let bytes = value.to_bytes_be();
+    let mut result = [0u8; 32];
+    result[32 - bytes.len()..].copy_from_slice(&bytes);
+    result
+}
+
+fn fe_to_biguint<E: Engine>(value: &E::Fr) -> BigUint {
+    BigUint::from_bytes_le(&fe_to_le_bytes::<E>(value))
+}
+
+// Reduced modulo the native modulus.
+fn biguint_to_fe<E: Engine>(value: &BigUint) -> E::Fr {
+    E::Fr::from_str(&value.to_string()).unwrap()
+}
+
+// Element of the BLS field in the circuit: little-endian limbs of 64 bits, every limb is at
+// most `limb_max`. The value is not reduced, limbs of sums grow above 64 bits,
+// only the congruences modulo q are enforced, see `enforce_congruent`.
+#[derive(Clone)]
+pub(crate) struct Element<E: Engine> {
+    limbs: Vec<Num<E>>,
+    limb_max: BigUint,
+}
+
+impl<E: Engine> Element<E> {
+    // At least 4 limbs, more for a larger value.
+    fn constant(value: &BigUint) -> Self {
+        let mask = (BigUint::from(1u32) << LIMB_BITS) - 1u32;
+        let len = LIMBS.max(value.bits().div_ceil(LIMB_BITS as u64) as usize);
+        let limbs: Vec<BigUint> = (0..len)
+            .map(|index| (value >> (LIMB_BITS * index)) & &mask)
+            .collect();
+
+        Self {
+            limb_max: limbs.iter().max().cloned().unwrap_or_default(),
+            limbs: limbs
+                .iter()
+                .map(|limb| Num::Constant(biguint_to_fe::<E>(limb)))
+                .collect(),
+        }
+    }
+
+    // `limbs` limbs range checked by bytes.
+    // circuit arithmetic
+    fn alloc<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        value: Option<BigUint>,
+        limbs: usize,
+        range_table: &RangeTable,
+    ) -> Result<Self, SynthesisError> {
+        let value = value.map(|value| value.to_bytes_le());
+        let bytes: Vec<Option<u8>> = (0..limbs * LIMB_BITS / 8)
+            .map(|index| {
+                value
+                    .as_ref()
+                    .map(|value| value.get(index).copied().unwrap_or(0))
+            })
+            .collect();
+        let bytes = allocate_and_prove_bytes(&bytes, bytes.len(), cs, range_table, false)?;
+
+        Self::from_le_bytes(cs, &bytes)
+    }
+
+    // 4 limbs of a value below q, as the point evaluation precompile takes it.
+    // The value plus the range checked difference to q - 1 is q - 1 limb by limb with
+    // boolean carries, every sum is below 2^66, so the limb equations hold as integers.
+    // circuit arithmetic
+    pub(crate) fn alloc_reduced<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        value: Option<BigUint>,
+        range_table: &RangeTable,
+    ) -> Result<Self, SynthesisError> {
+        let max = modulus() - 1u32;
+        let difference_value = value
+            .as_ref()
+            .map(|value| max.checked_sub(value).unwrap_or_default());
+        let element = Self::alloc(cs, value, LIMBS, range_table)?;
+        let difference = Self::alloc(cs, difference_value, LIMBS, range_table)?;
+        let max = Self::constant(&max);
+
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+        let mut minus_limb_shift = biguint_to_fe::<E>(&(BigUint::from(1u32) << LIMB_BITS));
+        minus_limb_shift.negate();
+        let mut carry = Boolean::constant(false);
+        let mut carry_value = Some(BigUint::default());
+        for index in 0..LIMBS {
+            let mut lc = LinearCombination::zero();
+            lc.add_assign_number_with_coeff(&element.limbs[index], E::Fr::one());
+            lc.add_assign_number_with_coeff(&difference.limbs[index], E::Fr::one());
+            lc.add_assign_boolean_with_coeff(&carry, E::Fr::one());
+            lc.add_assign_number_with_coeff(&max.limbs[index], minus_one);
+
+            carry_value = [&element.limbs[index], &difference.limbs[index]]
+                .iter()
+                .map(|limb| limb.get_value().map(|limb| fe_to_biguint::<E>(&limb)))
+                .sum::<Option<BigUint>>()
+                .zip(carry_value)
+                .map(|(sum, carry)| (sum + carry) >> LIMB_BITS);
+            // no carry out of the last limb
+            carry = if index + 1 < LIMBS {
+                Boolean::Is(AllocatedBit::alloc(
+                    cs,
+                    carry_value
+                        .as_ref()
+                        .map(|carry| *carry == BigUint::from(1u32)),
+                )?)
+            } else {
+                Boolean::constant(false)
+            };
+            lc.add_assign_boolean_with_coeff(&carry, minus_limb_shift);
+            lc.into_num(cs)?.enforce_equal(cs, &Num::zero())?;
+        }
+
+        Ok(element)
+    }
+
+    // circuit arithmetic
+    fn from_le_bytes<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        bytes: &[Byte<E>],
+    ) -> Result<Self, SynthesisError> {
+        let limbs = bytes
+            .chunks(LIMB_BITS / 8)
+            .map(|limb| le_bytes_to_num(cs, limb))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            limbs,
+            limb_max: (BigUint::from(1u32) << LIMB_BITS) - 1u32,
+        })
+    }
+
+    fn value(&self) -> Option<BigUint> {
+        let mut result = BigUint::default();
+        for limb in self.limbs.iter().rev() {
+            result = (result << LIMB_BITS) + fe_to_biguint::<E>(&limb.get_value()?);
+        }
+        Some(result)
+    }
+
+    fn limb_values(&self) -> Option<Vec<BigInt>> {
+        self.limbs
+            .iter()
+            .map(|limb| Some(BigInt::from(fe_to_biguint::<E>(&limb.get_value()?))))
+            .collect()
+    }
+
+    // Upper bound of the value.
+    fn max(&self) -> BigUint {
+        (0..self.limbs.len())
+            .map(|index| &self.limb_max << (LIMB_BITS * index))
+            .sum()
+    }
+
+    // Limb by limb, without carries.
+    // circuit arithmetic
+    fn add<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let limbs = self
+            .limbs
+            .iter()
+            .zip(other.limbs.iter())
+            .map(|(limb, other)| limb.add(cs, other))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            limbs,
+            limb_max: &self.limb_max + &other.limb_max,
+        })
+    }
+
+    // The value modulo the native modulus.
+    // circuit arithmetic
+    fn native<CS: ConstraintSystem<E>>(&self, cs: &mut CS) -> Result<Num<E>, SynthesisError> {
+        let shift = biguint_to_fe::<E>(&(BigUint::from(1u32) << LIMB_BITS));
+        let mut result = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for limb in self.limbs.iter() {
+            result.add_assign_number_with_coeff(limb, coeff);
+            coeff.mul_assign(&shift);
+        }
+        result.into_num(cs)
+    }
+}
+
+type Products<'a, E> = [(&'a Element<E>, &'a Element<E>)];
+
+// Enforce sum(lhs) == sum(rhs) modulo q, for sums of products of elements.
+//
+// With a quotient k and a constant multiple m of q, which keeps the quotient non-negative,
+//
+//     D = sum(lhs) + m * 1 - sum(rhs) - k * q == 0
+//
+// D is zero modulo 2^(64 * columns) by the limb columns with the signed carries, and zero
+// modulo the native modulus p by the native values. The bounds of the limbs keep
+// |D| < 2^(64 * columns) * p, so D is zero as an integer.
+// circuit arithmetic
+fn enforce_congruent<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    lhs: &Products<E>,
+    rhs: &Products<E>,
+    range_table: &RangeTable,
+) -> Result<(), SynthesisError> {
+    let modulus = modulus();
+    let mut native_modulus = E::Fr::one();
+    native_modulus.negate();
+    let native_modulus = fe_to_biguint::<E>(&native_modulus) + 1u32;
+
+    let shift = (bound(rhs) / &modulus + 1u32) * &modulus;
+    let quotient_limbs = ((bound(lhs) + &shift) / &modulus)
+        .bits()
+        .div_ceil(LIMB_BITS as u64) as usize;
+    let quotient = match (sum(lhs), sum(rhs)) {
+        (Some(lhs), Some(rhs)) => Some((lhs + &shift - rhs) / &modulus),
+        _ => None,
+    };
+    let quotient = Element::alloc(cs, quotient, quotient_limbs, range_table)?;
+
+    let shift = Element::constant(&shift);
+    let one = Element::constant(&BigUint::from(1u32));
+    let modulus = Element::constant(&modulus);
+    let positive = [lhs, &[(&shift, &one)][..]].concat();
+    let negative = [rhs, &[(&quotient, &modulus)][..]].concat();
+
+    let difference_max = bound(&positive) + bound(&negative);
+    let mut columns = 1;
+    while &native_modulus << (LIMB_BITS * columns) <= difference_max {
+        columns += 1;
+    }
+
+    let mut column_lcs: Vec<LinearCombination<E>> =
+        (0..columns).map(|_| LinearCombination::zero()).collect();
+    let mut column_values = Some(vec![BigInt::default(); columns]);
+    for (a, b) in positive.iter() {
+        add_product_columns(cs, &mut column_lcs, &mut column_values, a, b, false)?;
+    }
+    for (a, b) in negative.iter() {
+        add_product_columns(cs, &mut column_lcs, &mut column_values, a, b, true)?;
+    }
+
+    // column + carry_in == carry * 2^64, the carries are shifted by 2^carry_bits to be non-negative
+    let column_max: BigUint = positive
+        .iter()
+        .chain(negative.iter())
+        .map(|(a, b)| &a.limb_max * &b.limb_max * LIMBS)
+        .sum();
+    let carry_bits = column_max.bits() as usize + 1 - LIMB_BITS;
+    let carry_offset = BigInt::from(1u32) << carry_bits;
+    let carry_offset_fe = biguint_to_fe::<E>(&(BigUint::from(1u32) << carry_bits));
+    let mut minus_carry_offset = carry_offset_fe;
+    minus_carry_offset.negate();
+    let limb_shift = biguint_to_fe::<E>(&(BigUint::from(1u32) << LIMB_BITS));
+    let mut minus_limb_shift = limb_shift;
+    minus_limb_shift.negate();
+    let mut carry_out_offset = carry_offset_fe;
+    carry_out_offset.mul_assign(&limb_shift);
+
+    let mut carry_in: Option<(Num<E>, Option<BigInt>)> = None;
+    for (column, mut lc) in column_lcs.into_iter().enumerate() {
+        let mut value = column_values.as_ref().map(|values| values[column].clone());
+        if let Some((carry, carry_value)) = carry_in {
+            lc.add_assign_number_with_coeff(&carry, E::Fr::one());
+            lc.add_assign_constant(minus_carry_offset);
+            value = value.zip(carry_value).map(|(value, carry)| value + carry);
+        }
+
+        let carry_value = value.map(|value| value >> LIMB_BITS);
+        let shifted_carry = Num::alloc(
+            cs,
+            carry_value.as_ref().map(|carry| {
+                biguint_to_fe::<E>(&(carry + &carry_offset).to_biguint().unwrap_or_default())
+            }),
+        )?;
+        enforce_fits_bytes(
+            cs,
+            &shifted_carry,
+            (carry_bits + 1).div_ceil(8),
+            range_table,
+        )?;
+        lc.add_assign_number_with_coeff(&shifted_carry, minus_limb_shift);
+        lc.add_assign_constant(carry_out_offset);
+        lc.into_num(cs)?.enforce_equal(cs, &Num::zero())?;
+
+        carry_in = Some((shifted_carry, carry_value));
+    }
+
+    // D == 0 modulo p
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    let mut native = LinearCombination::zero();
+    for (products, coeff) in [(&positive, E::Fr::one()), (&negative, minus_one)] {
+        for (a, b) in products.iter() {
+            let a = a.native(cs)?;
+            let b = b.native(cs)?;
+            native.add_assign_number_with_coeff(&a.mul(cs, &b)?, coeff);
+        }
+    }
+    native.into_num(cs)?.enforce_equal(cs, &Num::zero())
+}
+
+// Upper bound of the sum of products.
+fn bound<E: Engine>(products: &Products<E>) -> BigUint {
+    products.iter().map(|(a, b)| a.max() * b.max()).sum()
+}
+
+fn sum<E: Engine>(products: &Products<E>) -> Option<BigUint> {
+    products
+        .iter()
+        .map(|(a, b)| Some(a.value()? * b.value()?))
+        .sum()
+}
+
+// Add the limb products of a * b to the columns, negated if `negative`.
+// `values` are the values of the columns, if the witness is known.
+// circuit arithmetic
+fn add_product_columns<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    columns: &mut [LinearCombination<E>],
+    values: &mut Option<Vec<BigInt>>,
+    a: &Element<E>,
+    b: &Element<E>,
+    negative: bool,
+) -> Result<(), SynthesisError> {
+    let limb_values = a.limb_values().zip(b.limb_values());
+    if limb_values.is_none() {
+        *values = None;
+    }
+    let mut coeff = E::Fr::one();
+    if negative {
+        coeff.negate();
+    }
+
+    for (i, a_limb) in a.limbs.iter().enumerate() {
+        for (j, b_limb) in b.limbs.iter().enumerate() {
+            if i + j >= columns.len() {
+                break;
+            }
+            columns[i + j].add_assign_number_with_coeff(&a_limb.mul(cs, b_limb)?, coeff);
+
+            if let (Some(values), Some((a_values, b_values))) =
+                (values.as_mut(), limb_values.as_ref())
+            {
+                let product = &a_values[i] * &b_values[j];
+                if negative {
+                    values[i + j] -= product;
+                } else {
+                    values[i + j] += product;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use compression::{sha3, sha3::Digest};
+use franklin_crypto::bellman::{
+    compact_bn256::Bn256,
+    plonk::better_better_cs::cs::{
+        Assembly, ConstraintSystem, Index, PlonkCsWidth4WithNextStepParams, SynthesisMode,
+        Width4MainGateWithDNext,
+    },
+};
+
+use crate::{
+    config::CircuitConfig,
+    create_circuit_setup,
+    crs::{CompressionCrs, CrsSource},
+    error::Error,
+    serialization::{load_setup, load_vk, save_setup, save_vk},
+    synthesize_setup_assembly,
+    utils::fe_to_bytes,
+    CircuitSetup,
+};
+
+// Bump when the cached files or the key derivation change.
+const CACHE_VERSION: &[u8] = b"compression-setup-cache-v3";
+
+// On-disk cache of the setup and the verification key.
+//
+// `create_setup` and `VerificationKey::from_setup` are the slow part of the setup.
+// The circuit is synthesized on every call, which is cheap next to them, and the entries are
+// keyed by the config, the `shape_hash` of the circuit and the loaded CRS points. A change of
+// the circuit code or of the CRS file gets a new entry instead of a stale one.
+pub struct SetupCache {
+    dir: PathBuf,
+}
+
+impl SetupCache {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn setup(&self, config: CircuitConfig, crs: &CrsSource) -> Result<CircuitSetup, Error> {
+        let assembly = synthesize_setup_assembly(config)?;
+        let shape = shape_hash(&assembly);
+        let crs = crs.load(assembly.n().next_power_of_two())?;
+
+        let key = cache_key(config, shape, &crs)?;
+        let setup_path = self.dir.join(format!("{key}.setup"));
+        let vk_path = self.dir.join(format!("{key}.vk"));
+
+        if setup_path.exists() && vk_path.exists() {
+            let setup = load_setup(&setup_path)?;
+            let vk = load_vk(&vk_path)?;
+            return CircuitSetup::with_crs(config, setup, vk, shape, crs);
+        }
+
+        let setup = create_circuit_setup(config, crs, &assembly)?;
+
+        // Written to temporary files first, so an interrupted run doesn't leave a broken entry.
+        fs::create_dir_all(&self.dir)?;
+        let setup_tmp = setup_path.with_extension("setup.tmp");
+        save_setup(&setup.setup, &setup_tmp)?;
+        let vk_tmp = vk_path.with_extension("vk.tmp");
+        save_vk(&setup.vk, &vk_tmp)?;
+        fs::rename(setup_tmp, setup_path)?;
+        fs::rename(vk_tmp, vk_path)?;
+
+        Ok(setup)
+    }
+}
+
+// The 32 bytes written by the setup, see `shape_hash`.
+pub fn load_shape<P: AsRef<Path>>(path: P) -> Result<[u8; 32], Error> {
+    let bytes = fs::read(path)?;
+    bytes.try_into().map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "expected 32 bytes").into()
+    })
+}
+
+// Hex encoded Keccak256 of the cache version, the config, the circuit shape and the CRS.
+// The CRS is hashed as loaded for the domain, so the key follows the contents of the file
+// rather than its path, and only the points the setup uses are read.
+pub(crate) fn cache_key(
+    config: CircuitConfig,
+    shape: [u8; 32],
+    crs: &CompressionCrs,
+) -> Result<String, Error> {
+    let mut hasher = sha3::Keccak256::new();
+    hasher.update(CACHE_VERSION);
+    hasher.update(serde_json::to_vec(&config).expect("config is serializable"));
+    hasher.update(shape);
+    crs.write(&mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+// Keccak256 of everything the setup depends on: the sizes, the variables and the setup
+// polynomials of every gate, the lookup selectors and the rows of the tables.
+// Hash maps are absorbed in the order of their keys, so the hash is deterministic, and
+// the setup and the proving assemblies of the same circuit have the same one.
+pub(crate) fn shape_hash<S: SynthesisMode>(
+    assembly: &Assembly<Bn256, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext, S>,
+) -> [u8; 32] {
+    let mut hasher = sha3::Keccak256::new();
+    for size in [assembly.n(), assembly.num_inputs, assembly.num_aux] {
+        hasher.update((size as u64).to_le_bytes());
+    }
+
+    for storage in [&assembly.inputs_storage, &assembly.aux_storage] {
+        let state: BTreeMap<String, _> = storage
+            .state_map
+            .iter()
+            .map(|(id, variables)| (format!("{id:?}"), variables))
+            .collect();
+        for (id, variables) in state {
+            hasher.update(id);
+            let mut bytes = Vec::with_capacity(9 * variables.len());
+            for variable in variables.iter() {
+                let (kind, index) = match variable.get_unchecked() {
+                    Index::Input(index) => (0u8, index),
+                    Index::Aux(index) => (1u8, index),
+                };
+                bytes.push(kind);
+                bytes.extend((index as u64).to_le_bytes());
+            }
+            hasher.update(bytes);
+        }
+
+        let setup: BTreeMap<String, _> = storage
+            .setup_map
+            .iter()
+            .map(|(id, values)| (format!("{id:?}"), values))
+            .collect();
+        for (id, values) in setup {
+            hasher.update(id);
+            let bytes: Vec<u8> = values
+                .iter()
+                .flat_map(|value| fe_to_bytes::<Bn256>(value))
+                .collect();
+            hasher.update(bytes);
+        }
+    }
+
+    let selectors: BTreeMap<&String, _> = assembly.table_selectors.iter().collect();
+    for (name, selector) in selectors {
+        hasher.update(name);
+        let bits: Vec<u8> = selector.iter().map(u8::from).collect();
+        hasher.update(bits);
+
+        let table = assembly
+            .get_table(name)
+            .expect("selector of an existing table");
+        for column in table.get_table_values_for_polys() {
+            let bytes: Vec<u8> = column
+                .iter()
+                .flat_map(|value| fe_to_bytes::<Bn256>(value))
+                .collect();
+            hasher.update(bytes);
+        }
+    }
+
+    hasher.finalize().into()
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{ADDRESS_SIZE, STORAGE_KEY_OR_VALUE_SIZE};
+
+// How the statement of the circuit is exposed to the verifier.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PublicInputMode {
+    // Every byte of both hashes is a separate public input(64 inputs).
+    #[default]
+    Bytes,
+    // Both hashes and the compressed data length are hashed with Poseidon into one field element,
+    // see `utils::public_input_commitment`.
+    Commitment,
+    // Both hashes are packed by 31 bytes into field elements(3 inputs),
+    // see `utils::packed_public_inputs`.
+    Packed,
+}
+
+// How `compressed_data_hash` commits to the compressed data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CommitmentScheme {
+    // Keccak256 over the bytes.
+    #[default]
+    Keccak,
+    // Poseidon sponge over the compressed data padded with zeroes to `max_compressed_data_size`,
+    // packed by 31 bytes into field elements, followed by the data length.
+    // The result is a field element, serialized as 32 big-endian bytes.
+    // Much cheaper in-circuit than keccak, see `utils::compressed_data_commitment`.
+    Poseidon,
+}
+
+// Options of the circuit which change its shape, so the setup depends on them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CircuitConfig {
+    pub public_input: PublicInputMode,
+    pub commitment: CommitmentScheme,
+    // Number of transitions in a batch.
+    pub capacity: usize,
+    // Also prove that the transitions move the state tree from the old to the new root,
+    // both roots are public inputs. The witness needs `CompressionWitness::with_state_update`.
+    #[serde(default)]
+    pub state_update: bool,
+    // Also prove the value of the EIP-4844 blob polynomial of the compressed data at a point,
+    // both are public inputs after the state roots. The witness needs
+    // `CompressionWitness::with_blob_point`, see `blob::evaluate_blob`.
+    // Needs `CommitmentScheme::Poseidon`, the point is derived from the compressed data hash.
+    #[serde(default)]
+    pub blob_evaluation: bool,
+    // Also prove that the transitions are strictly increasing by (address, key), so a batch
+    // can't repeat a slot, see `StorageTransition::canonicalize`.
+    #[serde(default)]
+    pub canonical_order: bool,
+    // Range check two bytes per lookup row with a 2^16 rows table instead of one byte per row
+    // with a 2^8 rows one. The table rows are in the domain too, so it only pays off when
+    // the circuit is far above 2^16 gates, compare the `profile` of both.
+    #[serde(default)]
+    pub pair_range_table: bool,
+}
+
+impl Default for CircuitConfig {
+    fn default() -> Self {
+        Self {
+            public_input: PublicInputMode::default(),
+            commitment: CommitmentScheme::default(),
+            capacity: 1,
+            state_update: false,
+            blob_evaluation: false,
+            canonical_order: false,
+            pair_range_table: false,
+        }
+    }
+}
+
+// Every transition is encoded as an address, a key and a value word.
+pub const WORDS_PER_TRANSITION: usize = 3;
+pub const TRANSITION_SIZE: usize = ADDRESS_SIZE + 2 * STORAGE_KEY_OR_VALUE_SIZE;
+// The longest encoding of a transition: tagged address, tag 0 key and tag 0 value.
+const MAX_COMPRESSED_TRANSITION_SIZE: usize =
+    1 + ADDRESS_SIZE + 2 * (1 + STORAGE_KEY_OR_VALUE_SIZE);
+
+impl CircuitConfig {
+    pub fn words(&self) -> usize {
+        self.capacity * WORDS_PER_TRANSITION
+    }
+
+    pub fn max_data_size(&self) -> usize {
+        self.capacity * TRANSITION_SIZE
+    }
+
+    // Words are read 33 bytes at a time, the address word of the last transition reads
+    // into the key and the value, so no padding is needed after the longest encoding.
+    // An address of the previous transition(tag 3) is 20 bytes shorter than the longest
+    // encoding, so its 21 bytes are in the data too.
+    pub fn max_compressed_data_size(&self) -> usize {
+        self.capacity * MAX_COMPRESSED_TRANSITION_SIZE
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use franklin_crypto::bellman::{
+    compact_bn256::{Bn256, Fq, Fq2, FqRepr, G1Affine, G2Affine},
+    kate_commitment::{Crs, CrsForMonomialForm},
+    CurveAffine, EncodedPoint, PrimeField,
+};
+
+use crate::error::Error;
+
+pub type CompressionCrs = Crs<Bn256, CrsForMonomialForm>;
+
+// Where to take the universal setup(monomial form SRS) from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CrsSource {
+    File {
+        path: PathBuf,
+        format: CrsFormat,
+    },
+    // Deterministic `crs_42`, everybody knows the toxic waste. Only for tests and local runs.
+    #[cfg(any(test, feature = "insecure-dev"))]
+    InsecureDev,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrsFormat {
+    // `Crs::write` encoding, e.g. the `setup_2^N.key` files.
+    Bellman,
+    // One `transcriptNN.dat` file of the Aztec Ignition ceremony.
+    Ignition,
+    // `challenge` file of the perpetual powers of tau ceremony.
+    PowersOfTau,
+}
+
+#[derive(Debug)]
+pub enum CrsError {
+    // The file doesn't have enough G1 powers for the circuit domain.
+    NotEnoughPoints { available: usize, required: usize },
+    // The file is not in the expected format.
+    InvalidFile(String),
+}
+
+impl fmt::Display for CrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrsError::NotEnoughPoints {
+                available,
+                required,
+            } => write!(
+                f,
+                "CRS has {available} G1 points, but the circuit domain needs {required}"
+            ),
+            CrsError::InvalidFile(reason) => write!(f, "invalid CRS file: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CrsError {}
+
+impl CrsSource {
+    pub fn file<P: AsRef<Path>>(path: P, format: CrsFormat) -> Self {
+        CrsSource::File {
+            path: path.as_ref().to_path_buf(),
+            format,
+        }
+    }
+
+    // Load the first `domain_size` G1 powers and [1]_2, [x]_2.
+    pub fn load(&self, domain_size: usize) -> Result<CompressionCrs, Error> {
+        match self {
+            CrsSource::File { path, format } => {
+                let mut reader = BufReader::new(File::open(path)?);
+                let (g1, g2) = match format {
+                    CrsFormat::Bellman => read_bellman(&mut reader, domain_size)?,
+                    CrsFormat::Ignition => read_ignition(&mut reader, domain_size)?,
+                    CrsFormat::PowersOfTau => read_powers_of_tau(&mut reader, domain_size)?,
+                };
+                Ok(CompressionCrs::new(g1, g2))
+            }
+            #[cfg(any(test, feature = "insecure-dev"))]
+            CrsSource::InsecureDev => {
+                let worker = franklin_crypto::bellman::worker::Worker::new();
+                Ok(CompressionCrs::crs_42(domain_size, &worker))
+            }
+        }
+    }
+}
+
+fn check_points(available: usize, required: usize) -> Result<(), CrsError> {
+    if available < required {
+        return Err(CrsError::NotEnoughPoints {
+            available,
+            required,
+        });
+    }
+    Ok(())
+}
+
+fn read_bellman<R: Read>(
+    reader: &mut R,
+    domain_size: usize,
+) -> Result<(Vec<G1Affine>, Vec<G2Affine>), Error> {
+    let crs = CompressionCrs::read(reader)?;
+    check_points(crs.g1_bases.len(), domain_size)?;
+    if crs.g2_monomial_bases.len() < 2 {
+        return Err(CrsError::InvalidFile("expected [1]_2 and [x]_2".to_owned()).into());
+    }
+
+    Ok((
+        crs.g1_bases[..domain_size].to_vec(),
+        crs.g2_monomial_bases[..2].to_vec(),
+    ))
+}
+
+// Ignition transcript: a big-endian manifest followed by the G1 points starting from [x]_1
+// and then the G2 points starting from [x]_2. Coordinates are 4 big-endian u64 limbs,
+// the least significant limb first, not in Montgomery form.
+fn read_ignition<R: Read>(
+    reader: &mut R,
+    domain_size: usize,
+) -> Result<(Vec<G1Affine>, Vec<G2Affine>), Error> {
+    let mut manifest = [0u32; 7];
+    for field in manifest.iter_mut() {
+        *field = read_u32(reader)?;
+    }
+    let [transcript_number, _, _, _, num_g1_points, num_g2_points, start_from] = manifest;
+    if transcript_number != 0 || start_from != 0 {
+        return Err(CrsError::InvalidFile(
+            "only the first transcript starts from [x]_1".to_owned(),
+        )
+        .into());
+    }
+    // The generator is not in the transcript.
+    check_points(num_g1_points as usize + 1, domain_size)?;
+    if num_g2_points == 0 {
+        return Err(CrsError::InvalidFile("the transcript has no [x]_2".to_owned()).into());
+    }
+
+    let mut g1 = Vec::with_capacity(domain_size);
+    g1.push(G1Affine::one());
+    for _ in 1..domain_size {
+        let x = read_ignition_fq(reader)?;
+        let y = read_ignition_fq(reader)?;
+        g1.push(G1Affine::from_xy_checked(x, y).map_err(invalid_point)?);
+    }
+
+    // skip the rest of the G1 points
+    let skipped = (num_g1_points as u64 + 1 - domain_size as u64) * 64;
+    io::copy(&mut reader.by_ref().take(skipped), &mut io::sink())?;
+
+    let x = read_ignition_fq2(reader)?;
+    let y = read_ignition_fq2(reader)?;
+    let x_g2 = G2Affine::from_xy_checked(x, y).map_err(invalid_point)?;
+
+    Ok((g1, vec![G2Affine::one(), x_g2]))
+}
+
+// Powers of tau challenge: 64 bytes of the previous response hash, 2^(p + 1) - 1 G1 powers,
+// 2^p G2 powers, 2^p alpha and beta G1 powers and beta in G2, all the points are uncompressed.
+fn read_powers_of_tau<R: Read + Seek>(
+    reader: &mut R,
+    domain_size: usize,
+) -> Result<(Vec<G1Affine>, Vec<G2Affine>), Error> {
+    const HASH_SIZE: u64 = 64;
+    const G1_SIZE: u64 = 64;
+    const G2_SIZE: u64 = 128;
+
+    let len = reader.seek(SeekFrom::End(0))?;
+    // the hash and the missing last G1 power cancel out
+    let powers = len.saturating_sub(G2_SIZE) / (2 * G1_SIZE + G2_SIZE + 2 * G1_SIZE);
+    if !powers.is_power_of_two()
+        || len
+            != HASH_SIZE + (2 * powers - 1) * G1_SIZE + powers * (G2_SIZE + 2 * G1_SIZE) + G2_SIZE
+    {
+        return Err(CrsError::InvalidFile(format!("unexpected file length {len}")).into());
+    }
+    let g1_powers = 2 * powers - 1;
+    check_points(g1_powers as usize, domain_size)?;
+
+    reader.seek(SeekFrom::Start(HASH_SIZE))?;
+    let mut g1 = Vec::with_capacity(domain_size);
+    for _ in 0..domain_size {
+        let mut encoded = <G1Affine as CurveAffine>::Uncompressed::empty();
+        reader.read_exact(encoded.as_mut())?;
+        g1.push(encoded.into_affine().map_err(invalid_point)?);
+    }
+
+    reader.seek(SeekFrom::Start(HASH_SIZE + g1_powers * G1_SIZE))?;
+    let mut g2 = Vec::with_capacity(2);
+    for _ in 0..2 {
+        let mut encoded = <G2Affine as CurveAffine>::Uncompressed::empty();
+        reader.read_exact(encoded.as_mut())?;
+        g2.push(encoded.into_affine().map_err(invalid_point)?);
+    }
+
+    if g1[0] != G1Affine::one() || g2[0] != G2Affine::one() {
+        return Err(
+            CrsError::InvalidFile("the first powers are not the generators".to_owned()).into(),
+        );
+    }
+
+    Ok((g1, g2))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn read_ignition_fq<R: Read>(reader: &mut R) -> Result<Fq, Error> {
+    let mut repr = FqRepr::default();
+    for limb in repr.as_mut().iter_mut() {
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        *limb = u64::from_be_bytes(bytes);
+    }
+    Fq::from_repr(repr).map_err(invalid_point)
+}
+
+fn read_ignition_fq2<R: Read>(reader: &mut R) -> Result<Fq2, Error> {
+    let c0 = read_ignition_fq(reader)?;
+    let c1 = read_ignition_fq(reader)?;
+    Ok(Fq2 { c0, c1 })
+}
+
+fn invalid_point<T: fmt::Display>(error: T) -> Error {
+    CrsError::InvalidFile(format!("invalid point: {error}")).into()
+}
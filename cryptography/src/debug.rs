@@ -0,0 +1,220 @@
+use std::{collections::HashMap, fmt, ops::Range, sync::Arc};
+
+use franklin_crypto::bellman::{
+    compact_bn256::{Bn256, Fr},
+    plonk::better_better_cs::cs::{
+        ConstraintSystem, GateInternal, LookupTableApplication, PlonkCsWidth4WithNextStepParams,
+        PolyIdentifier, TrivialAssembly, Width4MainGateWithDNext,
+    },
+    Engine, Field,
+};
+
+use crate::{error::Error, main_circuit::CompressionCircuit};
+
+pub type DebugAssembly =
+    TrivialAssembly<Bn256, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>;
+
+// Labels of the circuit regions by the first aux gate of the region,
+// so a failing gate can be traced back to the check that produced it.
+#[derive(Debug, Default)]
+pub struct Regions {
+    starts: Vec<(usize, String)>,
+}
+
+impl Regions {
+    // Start a new region at the next gate.
+    pub fn enter<E: Engine, CS: ConstraintSystem<E>>(&mut self, cs: &CS, label: impl Into<String>) {
+        self.starts
+            .push((cs.get_current_aux_gate_number(), label.into()));
+    }
+
+    // Aux gate ranges of the regions in the circuit order, `gates` is the total number of gates.
+    pub fn spans(&self, gates: usize) -> impl Iterator<Item = (Range<usize>, &str)> {
+        self.starts
+            .iter()
+            .enumerate()
+            .map(move |(index, (start, label))| {
+                let end = self
+                    .starts
+                    .get(index + 1)
+                    .map(|(next, _)| *next)
+                    .unwrap_or(gates);
+                (*start..end, label.as_str())
+            })
+    }
+
+    // Label of the region which contains the aux gate.
+    pub fn label(&self, gate: usize) -> &str {
+        self.starts
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= gate)
+            .map(|(_, label)| label.as_str())
+            .unwrap_or("unlabeled")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GateKind {
+    MainGate,
+    Lookup(String),
+}
+
+// The first failing aux gate with the values of its variables:
+// a, b, c, d and d of the next gate for the main gate, the looked up row for lookups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsatisfiedGate {
+    pub gate: usize,
+    pub region: String,
+    pub kind: GateKind,
+    pub values: Vec<Fr>,
+}
+
+impl fmt::Display for UnsatisfiedGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            GateKind::MainGate => write!(f, "main gate {} in \"{}\"", self.gate, self.region)?,
+            GateKind::Lookup(table) => write!(
+                f,
+                "lookup into {table} at gate {} in \"{}\"",
+                self.gate, self.region
+            )?,
+        }
+        write!(f, " is not satisfied, values: [")?;
+        for (index, value) in self.values.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{value}")?;
+        }
+        write!(f, "]")
+    }
+}
+
+// Synthesize the circuit with the witness and find the first unsatisfied gate.
+pub fn check(circuit: &CompressionCircuit<Bn256>) -> Result<Option<UnsatisfiedGate>, Error> {
+    let mut assembly = DebugAssembly::new();
+    let mut regions = Regions::default();
+    circuit.synthesize_with_regions(&mut assembly, &mut regions)?;
+
+    Ok(first_unsatisfied_gate(&assembly, &regions))
+}
+
+// Gate by gate version of `is_satisfied` for the main gate and the lookups.
+// The assembly must not be finalized yet.
+pub fn first_unsatisfied_gate(
+    assembly: &DebugAssembly,
+    regions: &Regions,
+) -> Option<UnsatisfiedGate> {
+    let checker = GateChecker::new(assembly);
+    (0..assembly.num_aux_gates).find_map(|gate| {
+        checker
+            .check(assembly, gate)
+            .map(|(kind, values)| UnsatisfiedGate {
+                gate,
+                region: regions.label(gate).to_owned(),
+                kind,
+                values,
+            })
+    })
+}
+
+// Selectors and lookups of the assembly by gate, so single gates can be re-checked
+// after the witness is changed.
+pub struct GateChecker {
+    selectors: Vec<Vec<Fr>>,
+    lookups: HashMap<usize, Vec<(String, Arc<LookupTableApplication<Bn256>>)>>,
+}
+
+impl GateChecker {
+    pub fn new(assembly: &DebugAssembly) -> Self {
+        let main_gate = Width4MainGateWithDNext::default();
+        let selectors = main_gate
+            .setup_polynomials()
+            .into_iter()
+            .map(|id| {
+                assembly
+                    .aux_storage
+                    .setup_map
+                    .get(&id)
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        let mut lookups: HashMap<usize, Vec<_>> = HashMap::new();
+        for (table_name, selector) in assembly.table_selectors.iter() {
+            let table = assembly
+                .get_table(table_name)
+                .expect("selector of an existing table");
+            for gate in
+                (0..assembly.num_aux_gates).filter(|gate| selector.get(*gate).unwrap_or(false))
+            {
+                lookups
+                    .entry(gate)
+                    .or_default()
+                    .push((table_name.clone(), table.clone()));
+            }
+        }
+
+        Self { selectors, lookups }
+    }
+
+    // The kind of the failing constraint of the aux gate and its values, `None` if satisfied:
+    // a, b, c, d and d of the next gate for the main gate, the looked up row for lookups.
+    pub fn check(&self, assembly: &DebugAssembly, gate: usize) -> Option<(GateKind, Vec<Fr>)> {
+        let variable = |column: usize, gate: usize| -> Fr {
+            assembly
+                .aux_storage
+                .state_map
+                .get(&PolyIdentifier::VariablesPolynomial(column))
+                .and_then(|variables| variables.get(gate))
+                .map(|variable| assembly.get_value(*variable).unwrap_or(Fr::zero()))
+                .unwrap_or(Fr::zero())
+        };
+        let selector = |index: usize| -> Fr {
+            self.selectors[index]
+                .get(gate)
+                .copied()
+                .unwrap_or(Fr::zero())
+        };
+
+        // q_a * a + q_b * b + q_c * c + q_d * d + q_m * a * b + q_const + q_d_next * d_next == 0
+        let values = [
+            variable(0, gate),
+            variable(1, gate),
+            variable(2, gate),
+            variable(3, gate),
+            variable(3, gate + 1),
+        ];
+        let mut result = Fr::zero();
+        for (index, value) in values[..4].iter().enumerate() {
+            let mut term = selector(index);
+            term.mul_assign(value);
+            result.add_assign(&term);
+        }
+        let mut term = selector(4);
+        term.mul_assign(&values[0]);
+        term.mul_assign(&values[1]);
+        result.add_assign(&term);
+        result.add_assign(&selector(5));
+        let mut term = selector(6);
+        term.mul_assign(&values[4]);
+        result.add_assign(&term);
+
+        if !result.is_zero() {
+            return Some((GateKind::MainGate, values.to_vec()));
+        }
+
+        for (table_name, table) in self.lookups.get(&gate).into_iter().flatten() {
+            let row: Vec<Fr> = (0..table.width())
+                .map(|column| variable(column, gate))
+                .collect();
+            if !table.is_valid_entry(&row) {
+                return Some((GateKind::Lookup(table_name.clone()), row));
+            }
+        }
+
+        None
+    }
+}
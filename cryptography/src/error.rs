@@ -0,0 +1,60 @@
+use std::{fmt, io};
+
+use franklin_crypto::bellman::SynthesisError;
+
+use crate::crs::CrsError;
+use crate::debug::UnsatisfiedGate;
+use crate::witness::WitnessError;
+
+#[derive(Debug)]
+pub enum Error {
+    Witness(WitnessError),
+    Synthesis(SynthesisError),
+    Io(io::Error),
+    Crs(CrsError),
+    // The witness doesn't satisfy the circuit, the first failing gate.
+    Unsatisfied(UnsatisfiedGate),
+    // The setup, the verification key or the config were made for another circuit.
+    SetupMismatch,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Witness(error) => write!(f, "invalid witness: {error}"),
+            Error::Synthesis(error) => write!(f, "synthesis error: {error}"),
+            Error::Io(error) => write!(f, "io error: {error}"),
+            Error::Crs(error) => write!(f, "{error}"),
+            Error::Unsatisfied(gate) => write!(f, "unsatisfied circuit: {gate}"),
+            Error::SetupMismatch => {
+                write!(f, "the setup doesn't match the circuit, run setup again")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<WitnessError> for Error {
+    fn from(error: WitnessError) -> Self {
+        Error::Witness(error)
+    }
+}
+
+impl From<SynthesisError> for Error {
+    fn from(error: SynthesisError) -> Self {
+        Error::Synthesis(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<CrsError> for Error {
+    fn from(error: CrsError) -> Self {
+        Error::Crs(error)
+    }
+}
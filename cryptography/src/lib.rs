@@ -0,0 +1,257 @@
+use compression::StorageTransition;
+use franklin_crypto::bellman::{
+    // Bn256 - is the type for 2 eliptic curves that we need for pairing
+    // Fr - the base field that hosts eliptic curve G1
+    compact_bn256::{Bn256, Fr},
+
+    // Plonk is zero knowledge proof algorithm that we are using in this project.
+    // This algorithm uses smart ways to prove circuits through polynomial commitment.
+    plonk::{
+        better_better_cs::{
+            cs::{
+                Circuit,
+                PlonkCsWidth4WithNextStepParams, // constraint system parameters
+                Setup,
+                SetupAssembly,
+                TrivialAssembly,
+                Width4MainGateWithDNext,
+            },
+            proof::Proof,
+            setup::VerificationKey,
+            verifier,
+        },
+        commitments::transcript::{keccak_transcript::RollingKeccakTranscript, Transcript},
+    },
+    worker::Worker, // the helper for parallel proof calculations.
+    SynthesisError,
+};
+
+pub mod batch;
+pub mod blob;
+pub mod cache;
+pub mod config;
+pub mod crs;
+pub mod debug;
+pub mod error;
+pub mod main_circuit;
+mod memory;
+pub mod profile;
+pub mod serialization;
+pub mod solidity;
+pub mod state;
+pub mod transcript;
+pub mod utils;
+pub mod witness;
+
+pub use crate::batch::{verify_batch, verify_batch_with_transcript};
+pub use crate::blob::{blob_evaluation_point, evaluate_blob};
+pub use crate::cache::SetupCache;
+pub use crate::config::{CircuitConfig, CommitmentScheme, PublicInputMode};
+pub use crate::crs::{CompressionCrs, CrsError, CrsFormat, CrsSource};
+pub use crate::error::Error;
+pub use crate::main_circuit::CompressionCircuit;
+pub use crate::serialization::{CompressionProof, CompressionVerificationKey, Format};
+pub use crate::state::StateTree;
+pub use crate::transcript::PoseidonTranscript;
+pub use crate::witness::{CompressionWitness, WitnessError};
+
+#[cfg(test)]
+mod tests;
+
+// Everything the prover needs for the circuit with the given config.
+// The verifier only needs `vk`.
+pub struct CircuitSetup {
+    pub config: CircuitConfig,
+    pub setup: Setup<Bn256, CompressionCircuit<Bn256>>,
+    pub crs: CompressionCrs,
+    pub vk: VerificationKey<Bn256, CompressionCircuit<Bn256>>,
+    // `cache::shape_hash` of the circuit the setup was made for, checked by `prove`.
+    pub shape: [u8; 32],
+}
+
+impl CircuitSetup {
+    // Put together the setup, the key and the shape stored on disk, the CRS is loaded
+    // for the setup domain.
+    pub fn from_parts(
+        config: CircuitConfig,
+        setup: Setup<Bn256, CompressionCircuit<Bn256>>,
+        vk: VerificationKey<Bn256, CompressionCircuit<Bn256>>,
+        shape: [u8; 32],
+        crs: &CrsSource,
+    ) -> Result<Self, Error> {
+        let crs = crs.load(setup.n.next_power_of_two())?;
+        Self::with_crs(config, setup, vk, shape, crs)
+    }
+
+    pub(crate) fn with_crs(
+        config: CircuitConfig,
+        setup: Setup<Bn256, CompressionCircuit<Bn256>>,
+        vk: VerificationKey<Bn256, CompressionCircuit<Bn256>>,
+        shape: [u8; 32],
+        crs: CompressionCrs,
+    ) -> Result<Self, Error> {
+        if vk.n != setup.n || vk.num_inputs != setup.num_inputs {
+            return Err(Error::SetupMismatch);
+        }
+        Ok(Self {
+            config,
+            setup,
+            crs,
+            vk,
+            shape,
+        })
+    }
+}
+
+pub(crate) type CompressionSetupAssembly =
+    SetupAssembly<Bn256, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>;
+
+// Generate the setup and the verification key. It depends only on the circuit shape,
+// so the circuit is synthesized without a witness.
+pub fn setup(config: CircuitConfig, crs: &CrsSource) -> Result<CircuitSetup, Error> {
+    let assembly = synthesize_setup_assembly(config)?;
+
+    // This is the domain from Algebra - nonzero ring in which ab = 0 implies a = 0 or b = 0.
+    // Equivalently, a domain is a ring in which 0 is the only left zero divisor(or equivalently, the only right zero divisor).
+    // We need it for creating kate commitment.
+    let domain_size = assembly.n().next_power_of_two();
+
+    // This is the common reference string for the protocol.
+    // It is needed to capture the assumption that a trusted setup,
+    // in which all involved parties get access to the same string crs taken from some distribution D exists.
+    // Schemes proven secure in the CRS model are secure given that the setup was performed correctly,
+    // so it is taken from a powers of tau ceremony.
+    let crs = crs.load(domain_size)?;
+
+    create_circuit_setup(config, crs, &assembly)
+}
+
+pub(crate) fn synthesize_setup_assembly(
+    config: CircuitConfig,
+) -> Result<CompressionSetupAssembly, Error> {
+    let circuit = CompressionCircuit::<Bn256>::empty(config);
+
+    // assembly - the constraint system, we are using.
+    // SetupAssembly doesn't need the witness, only the gates and the permutation.
+    let mut assembly = CompressionSetupAssembly::new();
+    circuit.synthesize(&mut assembly)?;
+    assembly.finalize();
+
+    Ok(assembly)
+}
+
+pub(crate) fn create_circuit_setup(
+    config: CircuitConfig,
+    crs: CompressionCrs,
+    assembly: &CompressionSetupAssembly,
+) -> Result<CircuitSetup, Error> {
+    let worker = Worker::new();
+
+    // Setup construction for checking the commitment
+    let setup = assembly.create_setup::<CompressionCircuit<Bn256>>(&worker)?;
+
+    // This is kinda a black box, that contains all the necessary data characterizing
+    // the circuit data for the verifier
+    let vk = VerificationKey::from_setup(&setup, &worker, &crs)?;
+
+    Ok(CircuitSetup {
+        config,
+        setup,
+        crs,
+        vk,
+        shape: cache::shape_hash(assembly),
+    })
+}
+
+// Prove that the compressed transitions decompress into the transitions(CPU heavy).
+// The proof is made with `RollingKeccakTranscript`, which the Solidity verifier expects.
+pub fn prove(
+    setup: &CircuitSetup,
+    transitions: &[StorageTransition],
+) -> Result<Proof<Bn256, CompressionCircuit<Bn256>>, Error> {
+    prove_with_transcript::<RollingKeccakTranscript<Fr>>(setup, transitions)
+}
+
+pub fn prove_witness(
+    setup: &CircuitSetup,
+    witness: &CompressionWitness,
+) -> Result<Proof<Bn256, CompressionCircuit<Bn256>>, Error> {
+    prove_witness_with_transcript::<RollingKeccakTranscript<Fr>>(setup, witness)
+}
+
+// Same as `prove`, with the given Fiat-Shamir transcript, e.g. `PoseidonTranscript`
+// for the proofs which are verified in-circuit. The verifier must use the same transcript.
+pub fn prove_with_transcript<T: Transcript<Fr>>(
+    setup: &CircuitSetup,
+    transitions: &[StorageTransition],
+) -> Result<Proof<Bn256, CompressionCircuit<Bn256>>, Error> {
+    let witness = CompressionWitness::from_transitions(setup.config, transitions)?;
+    prove_witness_with_transcript::<T>(setup, &witness)
+}
+
+pub fn prove_witness_with_transcript<T: Transcript<Fr>>(
+    setup: &CircuitSetup,
+    witness: &CompressionWitness,
+) -> Result<Proof<Bn256, CompressionCircuit<Bn256>>, Error> {
+    let worker = Worker::new();
+
+    // From program to arithmetic circuits
+    let circuit = CompressionCircuit::<Bn256>::new(setup.config, witness);
+
+    // From arithmetic circuits to constraint system
+    let mut assembly =
+        TrivialAssembly::<Bn256, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>::new();
+    let mut regions = debug::Regions::default();
+    circuit.synthesize_with_regions(&mut assembly, &mut regions)?;
+    if !assembly.is_satisfied() {
+        return Err(match debug::first_unsatisfied_gate(&assembly, &regions) {
+            Some(gate) => Error::Unsatisfied(gate),
+            None => SynthesisError::Unsatisfiable.into(),
+        });
+    }
+    assembly.finalize();
+    if cache::shape_hash(&assembly) != setup.shape {
+        return Err(Error::SetupMismatch);
+    }
+
+    // From constraint system to polynomials and the proof
+    let proof = assembly.create_proof::<CompressionCircuit<Bn256>, T>(
+        &worker,
+        &setup.setup,
+        &setup.crs,
+        None,
+    )?;
+
+    Ok(proof)
+}
+
+// Every batch has its own proof, they are not aggregated into one recursively.
+// The recursion gadgets of franklin-crypto(`plonk::circuit::verifier_circuit`) verify
+// `better_cs` proofs without lookups, while this circuit is a `better_better_cs` one with
+// a range table. Aggregation needs an in-circuit verifier of these proofs(the lookup grand
+// product and a non-native pairing accumulator) and an algebraic transcript instead of
+// the keccak one.
+
+// Check the proof against the expected public inputs, see `CompressionWitness::public_inputs`.
+pub fn verify(
+    vk: &VerificationKey<Bn256, CompressionCircuit<Bn256>>,
+    proof: &Proof<Bn256, CompressionCircuit<Bn256>>,
+    public_inputs: &[Fr],
+) -> Result<bool, Error> {
+    verify_with_transcript::<RollingKeccakTranscript<Fr>>(vk, proof, public_inputs)
+}
+
+// Same as `verify` for the proofs made with `prove_with_transcript::<T>`.
+pub fn verify_with_transcript<T: Transcript<Fr>>(
+    vk: &VerificationKey<Bn256, CompressionCircuit<Bn256>>,
+    proof: &Proof<Bn256, CompressionCircuit<Bn256>>,
+    public_inputs: &[Fr],
+) -> Result<bool, Error> {
+    if proof.inputs != public_inputs {
+        return Ok(false);
+    }
+
+    let valid = verifier::verify::<Bn256, CompressionCircuit<Bn256>, T>(vk, proof, None)?;
+
+    Ok(valid)
+}
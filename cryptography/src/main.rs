@@ -1,138 +1,249 @@
-use franklin_crypto::bellman::{
-    // Bn256 - is the type for 2 eliptic curves that we need for pairing
-    // Fr - the base field that hosts eliptic curve G1
-    // FrRepr - represention of Fr for calculations
-    compact_bn256::{Bn256, Fr, FrRepr},
-
-    // Commitment based on a trusted setup and elliptic curve pairings
-    kate_commitment::{Crs, CrsForMonomialForm},
-
-    // Plonk is zero knowledge proof algorithm that we are using in this project.
-    // This algorithm uses smart ways to prove circuits through polynomial commitment.
-    plonk::{
-        better_better_cs::{
-            cs::{
-                Circuit,
-                PlonkCsWidth4WithNextStepParams, // constraint system parameters
-                TrivialAssembly,
-                Width4MainGateWithDNext,
-            },
-            setup::VerificationKey,
-            verifier::verify,
-        },
-        commitments::transcript::keccak_transcript::RollingKeccakTranscript,
-    },
-    worker::Worker, // the helper for parallel proof calculations.
-    PrimeField,     // This represents an element of a prime field.
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::exit,
 };
-use compression::{sha3, sha3::Digest, StorageTransition};
 
-mod main_circuit;
-pub mod utils;
-
-use crate::main_circuit::CompressionCircuit;
+use compression::StorageTransition;
+use cryptography::{
+    cache::load_shape, profile, prove, serialization, setup, solidity, verify, CircuitConfig,
+    CircuitSetup, CommitmentScheme, CompressionWitness, CrsFormat, CrsSource, PublicInputMode,
+};
+use franklin_crypto::bellman::compact_bn256::Bn256;
+use serde::Deserialize;
+
+const USAGE: &str = "\
+usage:
+    circuits-run setup --capacity N [--out DIR] [--public-input bytes|commitment|packed]
+                       [--commitment keccak|poseidon] [--pair-range-table] CRS
+    circuits-run prove --input diff.json [--setup DIR] [--out proof.bin] CRS
+                       (see examples/diff.json)
+    circuits-run verify --vk vk.bin --proof proof.bin [--input diff.json [--config FILE]]
+    circuits-run profile --capacity N [--public-input ...] [--commitment ...]
+                         [--pair-range-table]
+
+CRS is `--crs FILE [--crs-format bellman|ignition|powers-of-tau]`,
+or `--insecure-dev` when built with the insecure-dev feature.
+
+`setup` writes config.json, setup.bin, vk.bin, shape.bin and verifier.sol into DIR
+(default `setup`), `prove` rejects a setup made for another circuit.
+A proof covers exactly N transitions, diff.json must hold as many as the setup capacity.
+`verify` checks the proof against the public inputs it carries. With `--input` they must
+also be the inputs of diff.json for the config(default config.json next to the vk), which
+binds the proof to the data.
+Proofs and keys with a `.json` extension are written as JSON.";
+
+// One storage transition of diff.json, every field is a hex string.
+#[derive(Deserialize)]
+struct TransitionJson {
+    address: String,
+    key: String,
+    value: String,
+}
 
 fn main() {
-    // This is the data that we want to compress.
-    // These 3 words that are meant to be the state transition for ZK-rollup
-    let transitions = vec![StorageTransition {
-        address: [
-            211, 35, 126, 46, 74, 67, 213, 90, 55, 0, 12, 54, 222, 56, 77, 0, 132, 12, 1, 5,
-        ],
-        key: [
-            31, 8, 37, 27, 7, 64, 244, 1, 348 0, 6, 0, 274, 0, 0, 249, 0, 0, 17, 0, 0, 0, 234, 0,
-            122, 65, 33, 0, 4, 0, 4, 11,
-        ],
-        value: [
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-            272, 131, 3,
-        ],
-    }];
-
-    // We convert the data into bytes and compress it.
-    let data = StorageTransition::into_bytes(transitions.clone());
-    let compressed_data = StorageTransition::compress(transitions);
-
-    // Breakpoint 1:  from program to arithmetic circuits
-    let mut circuit = CompressionCircuit::<Bn256> {
-        data: data.clone().into_iter().map(|byte| Some(byte)).collect(),
-        compressed_data: compressed_data
-            .clone()
-            .into_iter()
-            .map(|byte| Some(byte))
-            .collect(),
-        data_hash: sha3::Keccak256::digest(data.as_slice())
-            .as_slice()
-            .to_vec()
-            .into_iter()
-            .map(|byte| Some(byte))
-            .collect::<Vec<Option<u8>>>(),
-        compressed_data_hash: sha3::Keccak256::digest(compressed_data.as_slice())
-            .as_slice()
-            .to_vec()
-            .into_iter()
-            .map(|byte| Some(byte))
-            .collect::<Vec<Option<u8>>>(),
-        compressed_data_len: Some(
-            Fr::from_repr(FrRepr([compressed_data.len() as u64, 0, 0, 0])).unwrap(),
-        ),
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, flags)) = args.split_first() else {
+        fail(USAGE);
     };
+    let flags = parse_flags(flags);
+
+    match command.as_str() {
+        "setup" => run_setup(&flags),
+        "prove" => run_prove(&flags),
+        "verify" => run_verify(&flags),
+        "profile" => run_profile(&flags),
+        _ => fail(USAGE),
+    }
+}
+
+fn run_setup(flags: &HashMap<String, String>) {
+    let config = circuit_config(flags);
+    let dir = PathBuf::from(flags.get("out").map(String::as_str).unwrap_or("setup"));
+
+    // Setup and verification key depend only on the circuit, not on the data
+    let setup = setup(config, &crs_source(flags)).unwrap_or_else(|error| fail(&error));
+    println!("Circuit size is {} gates", setup.setup.n);
+
+    fs::create_dir_all(&dir).unwrap_or_else(|error| fail(&error));
+    let config_file = File::create(dir.join("config.json")).unwrap_or_else(|error| fail(&error));
+    serde_json::to_writer_pretty(config_file, &config).unwrap_or_else(|error| fail(&error));
+    serialization::save_setup(&setup.setup, dir.join("setup.bin"))
+        .unwrap_or_else(|error| fail(&error));
+    serialization::save_vk(&setup.vk, dir.join("vk.bin")).unwrap_or_else(|error| fail(&error));
+    fs::write(dir.join("shape.bin"), setup.shape).unwrap_or_else(|error| fail(&error));
+    fs::write(
+        dir.join("verifier.sol"),
+        solidity::generate_verifier(&setup.vk),
+    )
+    .unwrap_or_else(|error| fail(&error));
+
+    println!("Setup is written to {}", dir.display());
+}
+
+fn run_prove(flags: &HashMap<String, String>) {
+    let dir = setup_dir(flags);
+    let out = flags.get("out").map(String::as_str).unwrap_or("proof.bin");
+    let transitions = read_transitions(Path::new(required(flags, "input")));
+
+    let config = read_config(&dir.join("config.json"));
+    let setup =
+        serialization::load_setup(dir.join("setup.bin")).unwrap_or_else(|error| fail(&error));
+    let vk = serialization::load_vk(dir.join("vk.bin")).unwrap_or_else(|error| fail(&error));
+    let shape = load_shape(dir.join("shape.bin")).unwrap_or_else(|error| fail(&error));
+    let setup = CircuitSetup::from_parts(config, setup, vk, shape, &crs_source(flags))
+        .unwrap_or_else(|error| fail(&error));
 
-    let old_worker = Worker::new();
-
-    // Breakpoint 2: from arithmetic circuits to constraint system
-    // assembly - the constraint system, we are using.
-    // initialize it as TrivialAssembly, that will be used to setup and proof synthesis
-    let mut assembly =
-        TrivialAssembly::<Bn256, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>::new();
-
-    circuit.synthesize(&mut assembly).expect("must work");
-    assert!(assembly.is_satisfied());
-
-    assembly.finalize();
-
-    // Breakpoint 3: from constraint system to polynomials
-
-    // This is the domain from Algebra - nonzero ring in which ab = 0 implies a = 0 or b = 0.
-    // Equivalently, a domain is a ring in which 0 is the only left zero divisor(or equivalently, the only right zero divisor).
-    // We need it for creating kate commitment.
-    let domain_size = assembly.n().next_power_of_two();
-
-    // This is the common reference string for the protocol.
-    // It is needed to capture the assumption that a trusted setup,
-    // in which all involved parties get access to the same string crs taken from some distribution D exists.
-    // Schemes proven secure in the CRS model are secure given that the setup was performed correctly.
-    let crs_mons = Crs::<Bn256, CrsForMonomialForm>::crs_42(domain_size, &old_worker);
-
-    // Breakpoint 4:  setup construction for checking the commitment
-    let setup = assembly
-        .create_setup::<CompressionCircuit<Bn256>>(&old_worker)
-        .unwrap();
-
-    // Breakpoint 5: proof generation(CPU heavy)
-    let proof = assembly
-        .clone()
-        .create_proof::<CompressionCircuit<Bn256>, RollingKeccakTranscript<Fr>>(
-            &old_worker,
-            &setup,
-            &crs_mons,
-            None,
-        )
-        .unwrap();
-
-    // Breakpoint 6: generation of the verification key
-    // This is kinda a black box, that contains all the necessary data characterizing
-    // the circuit data for the verifier
-    let vk = VerificationKey::from_setup(&setup, &old_worker, &crs_mons).unwrap();
-
-    // Breakpoint 7: verification of the proof
-    let valid =
-        verify::<Bn256, CompressionCircuit<Bn256>, RollingKeccakTranscript<Fr>>(&vk, &proof, None)
-            .unwrap();
+    // Proof generation(CPU heavy)
+    let proof = prove(&setup, &transitions).unwrap_or_else(|error| fail(&error));
+    serialization::save_proof(&proof, out).unwrap_or_else(|error| fail(&error));
+
+    println!("Proof is written to {out}");
+}
+
+fn run_verify(flags: &HashMap<String, String>) {
+    let vk_path = Path::new(required(flags, "vk"));
+    let vk = serialization::load_vk(vk_path).unwrap_or_else(|error| fail(&error));
+    let proof =
+        serialization::load_proof(required(flags, "proof")).unwrap_or_else(|error| fail(&error));
+
+    // With the transitions the statement is computed from them, the inputs of the proof
+    // are only compared.
+    let public_inputs = match flags.get("input") {
+        Some(input) => {
+            let transitions = read_transitions(Path::new(input));
+            let config = match flags.get("config") {
+                Some(config) => read_config(Path::new(config)),
+                None => read_config(&vk_path.with_file_name("config.json")),
+            };
+            CompressionWitness::from_transitions(config, &transitions)
+                .unwrap_or_else(|error| fail(&error))
+                .public_inputs::<Bn256>(config)
+        }
+        None => proof.inputs.clone(),
+    };
+    let valid = verify(&vk, &proof, &public_inputs).unwrap_or_else(|error| fail(&error));
 
     if valid {
         println!("Proof is verified successfully!🎉");
     } else {
-        println!("Proof verification failed!👎")
+        println!("Proof verification failed!👎");
+        exit(1);
     }
 }
+
+fn run_profile(flags: &HashMap<String, String>) {
+    let profile = profile::profile(circuit_config(flags)).unwrap_or_else(|error| fail(&error));
+    print!("{profile}");
+}
+
+fn circuit_config(flags: &HashMap<String, String>) -> CircuitConfig {
+    let capacity = required(flags, "capacity")
+        .parse()
+        .unwrap_or_else(|_| fail("--capacity must be a number"));
+    let public_input = match flags.get("public-input").map(String::as_str) {
+        None | Some("bytes") => PublicInputMode::Bytes,
+        Some("commitment") => PublicInputMode::Commitment,
+        Some("packed") => PublicInputMode::Packed,
+        Some(other) => fail(&format!("unknown public input mode {other}")),
+    };
+    let commitment = match flags.get("commitment").map(String::as_str) {
+        None | Some("keccak") => CommitmentScheme::Keccak,
+        Some("poseidon") => CommitmentScheme::Poseidon,
+        Some(other) => fail(&format!("unknown commitment scheme {other}")),
+    };
+
+    CircuitConfig {
+        public_input,
+        commitment,
+        capacity,
+        pair_range_table: flags.contains_key("pair-range-table"),
+        ..Default::default()
+    }
+}
+
+fn setup_dir(flags: &HashMap<String, String>) -> PathBuf {
+    PathBuf::from(flags.get("setup").map(String::as_str).unwrap_or("setup"))
+}
+
+fn read_config(path: &Path) -> CircuitConfig {
+    let file = File::open(path).unwrap_or_else(|error| fail(&error));
+    serde_json::from_reader(file).unwrap_or_else(|error| fail(&error))
+}
+
+fn crs_source(flags: &HashMap<String, String>) -> CrsSource {
+    if flags.contains_key("insecure-dev") {
+        #[cfg(feature = "insecure-dev")]
+        return CrsSource::InsecureDev;
+        #[cfg(not(feature = "insecure-dev"))]
+        fail("--insecure-dev needs the insecure-dev feature");
+    }
+
+    let format = match flags.get("crs-format").map(String::as_str) {
+        None | Some("bellman") => CrsFormat::Bellman,
+        Some("ignition") => CrsFormat::Ignition,
+        Some("powers-of-tau") => CrsFormat::PowersOfTau,
+        Some(other) => fail(&format!("unknown CRS format {other}")),
+    };
+    CrsSource::file(required(flags, "crs"), format)
+}
+
+fn read_transitions(path: &Path) -> Vec<StorageTransition> {
+    let file = File::open(path).unwrap_or_else(|error| fail(&error));
+    let transitions: Vec<TransitionJson> =
+        serde_json::from_reader(file).unwrap_or_else(|error| fail(&error));
+
+    transitions
+        .iter()
+        .map(|transition| StorageTransition {
+            address: parse_hex(&transition.address),
+            key: parse_hex(&transition.key),
+            value: parse_hex(&transition.value),
+        })
+        .collect()
+}
+
+// Fixed size big-endian hex string, with or without `0x`.
+fn parse_hex<const N: usize>(hex: &str) -> [u8; N] {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if hex.len() != 2 * N {
+        fail(&format!("expected {N} bytes in hex, got {hex}"));
+    }
+
+    let mut result = [0u8; N];
+    for (index, byte) in result.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * index..2 * index + 2], 16)
+            .unwrap_or_else(|_| fail(&format!("invalid hex {hex}")));
+    }
+    result
+}
+
+// `--name value` pairs, a flag without a value is stored with an empty one.
+fn parse_flags(args: &[String]) -> HashMap<String, String> {
+    let mut flags = HashMap::new();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let Some(name) = arg.strip_prefix("--") else {
+            fail(USAGE);
+        };
+        let value = match args.peek() {
+            Some(value) if !value.starts_with("--") => args.next().unwrap().clone(),
+            _ => String::new(),
+        };
+        flags.insert(name.to_owned(), value);
+    }
+    flags
+}
+
+fn required<'a>(flags: &'a HashMap<String, String>, name: &str) -> &'a str {
+    match flags.get(name) {
+        Some(value) if !value.is_empty() => value,
+        _ => fail(&format!("--{name} is required\n\n{USAGE}")),
+    }
+}
+
+fn fail<T: std::fmt::Display + ?Sized>(message: &T) -> ! {
+    eprintln!("{message}");
+    exit(2)
+}
@@ -17,17 +17,26 @@ pub use franklin_crypto::{
             commitments::transcript::{keccak_transcript::RollingKeccakTranscript, Transcript},
         },
         worker::Worker,
-        Engine, Field, PrimeField, ScalarEngine, SynthesisError,
+        Engine, Field, PrimeField, PrimeFieldRepr, ScalarEngine, SynthesisError,
     },
     plonk::circuit::{
         allocated_num::{AllocatedNum, Num},
         boolean::{AllocatedBit, Boolean},
         byte::Byte,
         custom_rescue_gate::Rescue5CustomGate,
+        linear_combination::LinearCombination,
     },
 };
 
+use rescue_poseidon::{CircuitGenericSponge, PoseidonParams};
+
+use crate::blob::enforce_blob_evaluation;
+use crate::config::{CircuitConfig, CommitmentScheme, PublicInputMode, TRANSITION_SIZE};
+use crate::debug::Regions;
+use crate::memory::{fe_to_usize, ByteMemory};
+use crate::state::{enforce_fits_bytes, enforce_state_update, le_bytes_to_num, StateWitness};
 use crate::utils::*;
+use crate::witness::CompressionWitness;
 
 // The main circuit structure.
 // PlonK circuit is composed by gates, which supports only multiplication and addition.
@@ -38,188 +47,565 @@ pub struct CompressionCircuit<E: Engine> {
     pub data_hash: Vec<Option<u8>>,
     pub compressed_data_hash: Vec<Option<u8>>,
     pub compressed_data_len: Option<E::Fr>,
+    pub state: Option<StateWitness>,
+    pub blob_point: Option<[u8; 32]>,
+    pub config: CircuitConfig,
+}
+
+impl<E: Engine> CompressionCircuit<E> {
+    // Circuit without a witness, enough for the setup generation.
+    pub fn empty(config: CircuitConfig) -> Self {
+        Self {
+            data: vec![None; config.max_data_size()],
+            compressed_data: vec![None; config.max_compressed_data_size()],
+            data_hash: vec![None; 32],
+            compressed_data_hash: vec![None; 32],
+            compressed_data_len: None,
+            state: None,
+            blob_point: None,
+            config,
+        }
+    }
+
+    pub fn new(config: CircuitConfig, witness: &CompressionWitness) -> Self {
+        Self {
+            data: witness.data.iter().map(|byte| Some(*byte)).collect(),
+            compressed_data: witness
+                .compressed_data
+                .iter()
+                .map(|byte| Some(*byte))
+                .collect(),
+            data_hash: witness.data_hash.iter().map(|byte| Some(*byte)).collect(),
+            compressed_data_hash: witness
+                .compressed_data_hash::<E>(config)
+                .into_iter()
+                .map(Some)
+                .collect(),
+            compressed_data_len: Some(witness.compressed_data_len::<E>()),
+            state: witness.state.clone(),
+            blob_point: witness.blob_point,
+            config,
+        }
+    }
 }
 
 impl<E: Engine> Circuit<E> for CompressionCircuit<E> {
     type MainGate = Width4MainGateWithDNext;
 
     fn synthesize<CS: ConstraintSystem<E>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        self.synthesize_with_regions(cs, &mut Regions::default())
+    }
+}
+
+impl<E: Engine> CompressionCircuit<E> {
+    // The circuit itself, every check is labeled in `regions` for the debugging, see `debug::check`.
+    pub(crate) fn synthesize_with_regions<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        regions: &mut Regions,
+    ) -> Result<(), SynthesisError> {
+        // The blob point is derived from `compressed_data_hash`, which is only proven
+        // with the Poseidon commitment, see `blob::blob_evaluation_point`.
+        if self.config.blob_evaluation && self.config.commitment != CommitmentScheme::Poseidon {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
         let columns = vec![
             PolyIdentifier::VariablesPolynomial(0),
             PolyIdentifier::VariablesPolynomial(1),
             PolyIdentifier::VariablesPolynomial(2),
         ];
 
-        let range_table = LookupTableApplication::new_range_table_of_width_3(8, columns.clone())?;
-        let range_table_name = range_table.functional_name();
-        cs.add_table(range_table)?;
+        let (table, bytes_per_row) = if self.config.pair_range_table {
+            (
+                LookupTableApplication::new_xor_table(8, columns.clone())?,
+                2,
+            )
+        } else {
+            (
+                LookupTableApplication::new_range_table_of_width_3(8, columns.clone())?,
+                1,
+            )
+        };
+        let range_table = RangeTable {
+            name: table.functional_name(),
+            bytes_per_row,
+        };
+        cs.add_table(table)?;
 
-        let _compressed_data_hash_bytes = allocate_and_prove_bytes(
+        let alloc_hashes_as_inputs = self.config.public_input == PublicInputMode::Bytes;
+        regions.enter(cs, "compressed data hash byte range check");
+        let compressed_data_hash_bytes = allocate_and_prove_bytes(
             &self.compressed_data_hash,
             32,
             cs,
-            range_table_name.as_str(),
-            true,
+            &range_table,
+            alloc_hashes_as_inputs,
         )?;
 
-        let _data_hash =
-            allocate_and_prove_bytes(&self.data_hash, 32, cs, range_table_name.as_str(), true)?;
+        regions.enter(cs, "data hash byte range check");
+        let data_hash_bytes = allocate_and_prove_bytes(
+            &self.data_hash,
+            32,
+            cs,
+            &range_table,
+            alloc_hashes_as_inputs,
+        )?;
 
+        regions.enter(cs, "compressed data byte range check");
         let compressed_data_bytes = allocate_and_prove_bytes(
             &self.compressed_data,
-            MAX_COMPRESSED_DATA_SIZE,
+            self.config.max_compressed_data_size(),
             cs,
-            range_table_name.as_str(),
+            &range_table,
             false,
         )?;
+        regions.enter(cs, "data byte range check");
         let data_bytes = allocate_and_prove_bytes(
             &self.data,
-            MAX_UNCOMPRESSED_DATA_SIZE,
+            self.config.max_data_size(),
             cs,
-            range_table_name.as_str(),
+            &range_table,
             false,
         )?;
 
-        // TODO: prove hashes correctness
+        let compressed_data_len = Num::alloc(cs, self.compressed_data_len)?;
 
-        let _compressed_data_len = Num::alloc(cs, self.compressed_data_len)?;
+        let mut extra_inputs = Vec::new();
+        if self.config.state_update {
+            regions.enter(cs, "state tree update");
+            let (old_root, new_root) =
+                enforce_state_update(cs, &data_bytes, self.state.as_ref(), &range_table)?;
+            extra_inputs.extend([old_root, new_root]);
+        }
+
+        if self.config.canonical_order {
+            regions.enter(cs, "canonical order");
+            enforce_canonical_order(cs, &data_bytes, &range_table)?;
+        }
+
+        if self.config.blob_evaluation {
+            regions.enter(cs, "blob evaluation");
+            extra_inputs.extend(enforce_blob_evaluation(
+                cs,
+                &compressed_data_bytes,
+                &compressed_data_len,
+                self.blob_point.as_ref(),
+                &range_table,
+            )?);
+        }
 
+        if self.config.public_input == PublicInputMode::Packed {
+            // Must match `utils::packed_public_inputs`. The bytes are range checked,
+            // so every input has the only decomposition.
+            regions.enter(cs, "packed public inputs");
+            let hashes = [
+                compressed_data_hash_bytes.as_slice(),
+                data_hash_bytes.as_slice(),
+            ]
+            .concat();
+            for packed in pack_bytes(cs, &hashes)? {
+                let input = Num::Variable(AllocatedNum::alloc_input(cs, || {
+                    packed.get_value().ok_or(SynthesisError::AssignmentMissing)
+                })?);
+                input.enforce_equal(cs, &packed)?;
+            }
+        }
+
+        // TODO: prove keccak hashes correctness. Until then `data_hash` and the keccak
+        // `compressed_data_hash` are free inputs, not bound to the data.
+        if self.config.commitment == CommitmentScheme::Poseidon {
+            // Must match `utils::compressed_data_commitment`.
+            regions.enter(cs, "poseidon commitment to the compressed data");
+            let mut commitment_input = pack_bytes(cs, &compressed_data_bytes)?;
+            commitment_input.push(compressed_data_len);
+            let commitment = circuit_poseidon_hash(cs, &commitment_input)?;
+
+            let hash = canonical_bytes_to_num(cs, &compressed_data_hash_bytes, &range_table)?;
+            hash.enforce_equal(cs, &commitment)?;
+        }
+
+        if self.config.public_input == PublicInputMode::Commitment {
+            // Must match `utils::public_input_commitment`.
+            regions.enter(cs, "public input commitment");
+            let mut public_data = pack_bytes(cs, &compressed_data_hash_bytes)?;
+            public_data.extend(pack_bytes(cs, &data_hash_bytes)?);
+            public_data.push(compressed_data_len);
+            public_data.extend(extra_inputs.iter().copied());
+            let commitment = circuit_poseidon_hash(cs, &public_data)?;
+
+            let input = Num::Variable(AllocatedNum::alloc_input(cs, || {
+                commitment
+                    .get_value()
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?);
+            input.enforce_equal(cs, &commitment)?;
+        }
+
+        if self.config.public_input != PublicInputMode::Commitment {
+            // after the inputs of the mode, see `CompressionWitness::public_inputs`
+            for extra in extra_inputs.iter() {
+                let input = Num::Variable(AllocatedNum::alloc_input(cs, || {
+                    extra.get_value().ok_or(SynthesisError::AssignmentMissing)
+                })?);
+                input.enforce_equal(cs, extra)?;
+            }
+        }
+
+        let mut compressed_data_memory = ByteMemory::new(compressed_data_bytes);
         let mut ptr = Num::zero();
         let zero = Num::zero();
         let one = Num::one();
+        let three = Num::Constant(E::Fr::from_str("3").unwrap());
+        let mut current_address: Option<&[Byte<E>]> = None;
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
 
-        for word in 0..MAX_WORDS {
-            let compressed_word = get_word_from_bytes(cs, &compressed_data_bytes, &ptr)?;
-            assert_eq!(compressed_word.len(), 33);
+        for word in 0..self.config.words() {
             let (uncompressed_pos, size) =
                 crate::utils::get_word_position_in_sequence_of_data(word);
-            let mut ok = Boolean::alloc(cs, Some(false))?;
             if size == 20 {
-                let mut is1 = Num::equals(cs, &compressed_word[0].inner, &one)?;
-                for i in 0..20 {
-                    let eq = Num::equals(
-                        cs,
-                        &data_bytes[uncompressed_pos + i].inner,
-                        &compressed_word[i + 1].inner,
-                    )?;
-                    is1 = Boolean::and(cs, &is1, &eq)?;
+                regions.enter(cs, format!("word {word} read"));
+                let compressed_word =
+                    get_word_from_bytes(cs, &mut compressed_data_memory, &ptr, 21)?;
+
+                // Tag 1 is followed by the 20 bytes of the address, tag 3 is the address of
+                // the previous transition(`current_address`) without any bytes.
+                regions.enter(cs, format!("word {word} address check"));
+                let is_new = Num::equals(cs, &compressed_word[0].inner, &one)?;
+                let is_previous = Num::equals(cs, &compressed_word[0].inner, &three)?;
+                let is_valid = Boolean::or(cs, &is_new, &is_previous)?;
+                Boolean::enforce_equal(cs, &is_valid, &Boolean::constant(true))?;
+                let mut is_new_num = LinearCombination::zero();
+                is_new_num.add_assign_boolean_with_coeff(&is_new, E::Fr::one());
+                let is_new_num = is_new_num.into_num(cs)?;
+
+                let address = &data_bytes[uncompressed_pos..uncompressed_pos + 20];
+                for (i, byte) in address.iter().enumerate() {
+                    // is_new ? word[i + 1] : current_address[i]
+                    let expected = match current_address {
+                        Some(current) => {
+                            let delta = compressed_word[i + 1]
+                                .inner
+                                .sub(cs, &current[i].inner)?
+                                .mul(cs, &is_new_num)?;
+                            current[i].inner.add(cs, &delta)?
+                        }
+                        None => compressed_word[i + 1].inner,
+                    };
+                    byte.inner.enforce_equal(cs, &expected)?;
+                }
+                if current_address.is_none() {
+                    // the first transition has no previous address
+                    Boolean::enforce_equal(cs, &is_new, &Boolean::constant(true))?;
                 }
-                ok = is1;
-                let _21 = Num::Constant(E::Fr::from_str("21").unwrap());
-                ptr = ptr.add(cs, &_21)?;
+                current_address = Some(address);
+
+                // tag + 20 bytes of a new address
+                let mut next_ptr = LinearCombination::zero();
+                next_ptr.add_assign_number_with_coeff(&ptr, E::Fr::one());
+                next_ptr.add_assign_constant(E::Fr::one());
+                next_ptr.add_assign_boolean_with_coeff(&is_new, E::Fr::from_str("20").unwrap());
+                ptr = next_ptr.into_num(cs)?;
             } else {
-                let mut is0 = Num::equals(cs, &compressed_word[0].inner, &zero)?;
-                for i in 0..32 {
-                    let eq = Num::equals(
+                // Tag 0 is followed by the 32 bytes of the value, tag 10 + n by the value
+                // without its n leading zeroes(n in 1..=32). Both are the same shift:
+                //
+                //     word[j] = j < zeroes ? 0 : compressed_data[ptr + 1 + j - zeroes]
+                //
+                // so every byte is one dynamic memory read instead of comparing the word
+                // with each of the 33 possible encodings,
+                // see `test_value_word_cost_against_the_branches`.
+                regions.enter(cs, format!("word {word} tag"));
+                let tag = compressed_data_memory.read(cs, &ptr)?;
+                let is_raw = Num::equals(cs, &tag.inner, &zero)?;
+                let mut minus_ten = E::Fr::from_str("10").unwrap();
+                minus_ten.negate();
+                let mut zeroes = LinearCombination::zero();
+                zeroes.add_assign_number_with_coeff(&tag.inner, E::Fr::one());
+                zeroes.add_assign_boolean_with_coeff(&is_raw.not(), minus_ten);
+                let zeroes = zeroes.into_num(cs)?;
+
+                // is_zero_byte[j] = j < zeroes, a prefix of ones of length `zeroes`,
+                // so `zeroes` is in 0..=32 and any other tag is rejected.
+                regions.enter(cs, format!("word {word} leading zeroes"));
+                let zeroes_value = zeroes
+                    .get_value()
+                    .map(|zeroes| fe_to_usize::<E>(&zeroes).unwrap_or(0));
+                let mut is_zero_byte: Vec<Boolean> = Vec::with_capacity(32);
+                let mut count = LinearCombination::zero();
+                for j in 0..32 {
+                    let bit = Boolean::Is(AllocatedBit::alloc(
                         cs,
-                        &data_bytes[uncompressed_pos + i].inner,
-                        &compressed_word[i + 1].inner,
-                    )?;
-                    is0 = Boolean::and(cs, &is0, &eq)?;
-                }
-                ok = Boolean::or(cs, &ok, &is0)?;
-
-                for i in 11..=42 {
-                    let i_num = Num::alloc(cs, Some(E::Fr::from_str(&format!("{}", i)).unwrap()))?;
-                    let mut is = Num::equals(cs, &compressed_word[0].inner, &i_num)?;
-                    for index in 0..32 {
-                        let eq;
-                        if index < i - 10 {
-                            eq = Num::equals(
-                                cs,
-                                &data_bytes[uncompressed_pos + index].inner,
-                                &zero,
-                            )?;
-                        } else {
-                            eq = Num::equals(
-                                cs,
-                                &data_bytes[uncompressed_pos + index].inner,
-                                &compressed_word[index - (i - 10) + 1].inner,
-                            )?;
-                        }
-                        is = Boolean::and(cs, &is, &eq)?;
+                        zeroes_value.map(|zeroes| j < zeroes),
+                    )?);
+                    if let Some(previous) = is_zero_byte.last() {
+                        // no zero byte after a value byte
+                        let gap = Boolean::and(cs, &bit, &previous.not())?;
+                        Boolean::enforce_equal(cs, &gap, &Boolean::constant(false))?;
                     }
-                    ok = Boolean::or(cs, &ok, &is)?;
+                    count.add_assign_boolean_with_coeff(&bit, E::Fr::one());
+                    is_zero_byte.push(bit);
                 }
-                let _33 = Num::alloc(cs, Some(E::Fr::from_str("33").unwrap()))?;
-                ptr = ptr.add(cs, &_33)?;
+                count.add_assign_number_with_coeff(&zeroes, minus_one);
+                count.into_num(cs)?.enforce_equal(cs, &zero)?;
+                // a leading-zero tag strips at least one zero(tag 10 is not valid)
+                Boolean::enforce_equal(cs, &is_zero_byte[0], &is_raw.not())?;
+
+                regions.enter(cs, format!("word {word} value bytes"));
+                for (j, is_zero) in is_zero_byte.iter().enumerate() {
+                    let mut is_value = LinearCombination::zero();
+                    is_value.add_assign_boolean_with_coeff(&is_zero.not(), E::Fr::one());
+                    let is_value = is_value.into_num(cs)?;
+
+                    // zero bytes read the tag again, so the address is always in the word
+                    let position = Num::Constant(E::Fr::from_str(&format!("{}", j + 1)).unwrap());
+                    let offset = position.sub(cs, &zeroes)?.mul(cs, &is_value)?;
+                    let address = ptr.add(cs, &offset)?;
+                    let byte = compressed_data_memory.read(cs, &address)?;
+
+                    let expected = byte.inner.mul(cs, &is_value)?;
+                    data_bytes[uncompressed_pos + j]
+                        .inner
+                        .enforce_equal(cs, &expected)?;
+                }
+
+                regions.enter(cs, format!("word {word} length"));
+                // tag + (32 - zeroes) bytes of the value
+                let _33 = Num::Constant(E::Fr::from_str("33").unwrap());
+                let word_len = _33.sub(cs, &zeroes)?;
+                ptr = ptr.add(cs, &word_len)?;
             }
             // TODO: Add 2 byte type
-            let true_bool = Boolean::alloc(cs, Some(true))?;
-            Boolean::enforce_equal(cs, &ok, &true_bool)?;
         }
+
+        // Every byte of the compressed data is consumed by the words, no trailing bytes.
+        regions.enter(cs, "compressed data length");
+        ptr.enforce_equal(cs, &compressed_data_len)?;
+
+        regions.enter(cs, "compressed data memory consistency");
+        compressed_data_memory.enforce_reads_consistency(cs, &range_table)?;
+
         Ok(())
     }
 }
 
+// The byte range check table of the circuit and the number of bytes one lookup row checks,
+// see `allocate_and_prove_bytes`.
+pub(crate) struct RangeTable {
+    pub name: String,
+    pub bytes_per_row: usize,
+}
+
 // Allocate byte array and prove tha values of bytes.
+//
+// One byte per lookup row with `new_range_table_of_width_3`: it contains the rows (x, 0, 0),
+// so only the first column is range checked and the other two must be zero.
+// Two bytes per row with the xor table of `CircuitConfig::pair_range_table`: it contains
+// the rows (x, y, x ^ y) for every pair of bytes, an odd byte is checked as (x, 0, x).
 // circuit arithmetic
-fn allocate_and_prove_bytes<E: Engine, CS: ConstraintSystem<E>>(
+pub(crate) fn allocate_and_prove_bytes<E: Engine, CS: ConstraintSystem<E>>(
     bytes: &Vec<Option<u8>>,
     len: usize,
     cs: &mut CS,
-    range_table_name: &str,
+    range_table: &RangeTable,
     alloc_as_inputs: bool,
 ) -> Result<Vec<Byte<E>>, SynthesisError> {
-    let mut result = Vec::with_capacity(bytes.len());
+    let values: Vec<Option<u8>> = (0..len)
+        .map(|i| *bytes.get(i).unwrap_or(&Some(0)))
+        .collect();
+    let mut result = Vec::with_capacity(len);
 
-    for i in 0..len {
-        let byte = bytes.get(i).unwrap_or(&Some(0));
+    for value in values.iter() {
+        let byte = value.map(|byte| E::Fr::from_str(&format!("{}", byte)).unwrap());
         let inner = if alloc_as_inputs {
             Num::Variable(AllocatedNum::alloc_input(cs, || {
-                Ok(E::Fr::from_str(&format!("{}", byte.unwrap())).unwrap())
+                byte.ok_or(SynthesisError::AssignmentMissing)
             })?)
         } else {
-            Num::alloc(
-                cs,
-                Some(E::Fr::from_str(&format!("{}", byte.unwrap())).unwrap()),
-            )?
+            Num::alloc(cs, byte)?
         };
 
-        let table = cs.get_table(range_table_name)?;
-        let num_keys_and_values = table.width();
+        result.push(Byte { inner });
+    }
+
+    let table = cs.get_table(&range_table.name)?;
+    let num_keys_and_values = table.width();
+    let bytes_per_row = range_table.bytes_per_row;
 
-        let var_zero = cs.get_explicit_zero()?;
-        let dummy = CS::get_dummy_variable();
+    let var_zero = cs.get_explicit_zero()?;
+    let dummy = CS::get_dummy_variable();
 
-        let inner_var = inner.get_variable().get_variable();
-        let vars = [inner_var, var_zero.clone(), var_zero.clone(), dummy];
+    for (row, chunk) in result.chunks(bytes_per_row).enumerate() {
+        let first = chunk[0].inner.get_variable().get_variable();
+        let vars = match chunk.get(1) {
+            Some(second) => {
+                let xor = values[2 * row]
+                    .zip(values[2 * row + 1])
+                    .map(|(a, b)| E::Fr::from_str(&format!("{}", a ^ b)).unwrap());
+                let xor = Num::alloc(cs, xor)?;
+                [
+                    first,
+                    second.inner.get_variable().get_variable(),
+                    xor.get_variable().get_variable(),
+                    dummy,
+                ]
+            }
+            None if bytes_per_row == 2 => [first, var_zero, first, dummy],
+            None => [first, var_zero, var_zero, dummy],
+        };
 
         cs.begin_gates_batch_for_step()?;
 
         cs.allocate_variables_without_gate(&vars, &[])?;
 
-        cs.apply_single_lookup_gate(&vars[..num_keys_and_values], table)?;
+        cs.apply_single_lookup_gate(&vars[..num_keys_and_values], table.clone())?;
         cs.end_gates_batch_for_step()?;
-
-        result.push(Byte { inner });
     }
 
     Ok(result)
 }
 
-// Read word by dynamic index from bytes array(O(n)).
+// Field element of 32 big-endian bytes(range checked), which must be below the modulus,
+// so the element has the only encoding and `commitment + p` is rejected.
+// Compared by 128-bit halves: high <= modulus_high, and low < modulus_low if they are equal.
+// circuit arithmetic
+fn canonical_bytes_to_num<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    bytes: &[Byte<E>],
+    range_table: &RangeTable,
+) -> Result<Num<E>, SynthesisError> {
+    const HALF: usize = 16;
+    let half = |cs: &mut CS, bytes: &[Byte<E>]| {
+        let bytes: Vec<Byte<E>> = bytes.iter().rev().copied().collect();
+        le_bytes_to_num(cs, &bytes)
+    };
+    let high = half(cs, &bytes[..HALF])?;
+    let low = half(cs, &bytes[HALF..])?;
+
+    let modulus = E::Fr::char();
+    let mut modulus_low = <E::Fr as PrimeField>::Repr::default();
+    modulus_low.as_mut()[..2].copy_from_slice(&modulus.as_ref()[..2]);
+    let mut max_low = E::Fr::from_repr(modulus_low).unwrap();
+    max_low.sub_assign(&E::Fr::one());
+    let mut modulus_high = modulus;
+    modulus_high.shr(8 * HALF as u32);
+    let modulus_high = Num::Constant(E::Fr::from_repr(modulus_high).unwrap());
+
+    // high <= modulus_high, the gap of a larger high wraps around the modulus
+    let gap = modulus_high.sub(cs, &high)?;
+    enforce_fits_bytes(cs, &gap, HALF, range_table)?;
+
+    // high == modulus_high => low < modulus_low
+    let is_max = Num::equals(cs, &high, &modulus_high)?;
+    let mut is_max_num = LinearCombination::zero();
+    is_max_num.add_assign_boolean_with_coeff(&is_max, E::Fr::one());
+    let is_max_num = is_max_num.into_num(cs)?;
+    let low_gap = Num::Constant(max_low).sub(cs, &low)?.mul(cs, &is_max_num)?;
+    enforce_fits_bytes(cs, &low_gap, HALF, range_table)?;
+
+    let mut shift = E::Fr::one();
+    for _ in 0..8 * HALF {
+        shift.double();
+    }
+    let mut result = LinearCombination::zero();
+    result.add_assign_number_with_coeff(&low, E::Fr::one());
+    result.add_assign_number_with_coeff(&high, shift);
+    result.into_num(cs)
+}
+
+// Every transition of `data`(range checked bytes) is above the previous one by (address, key),
+// compared as big-endian bytes. Native counterpart is `StorageTransition::is_canonical`.
+//
+// The first differing byte is the first position where the prefix of equal bytes ends,
+// the byte of the next transition minus the byte of the previous one there must be in 1..=256,
+// that is one byte range check of the difference minus one. Equal slots have no such byte.
+// circuit arithmetic
+fn enforce_canonical_order<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    data: &[Byte<E>],
+    range_table: &RangeTable,
+) -> Result<(), SynthesisError> {
+    let slot_size = ADDRESS_SIZE + STORAGE_KEY_OR_VALUE_SIZE;
+    let slots: Vec<&[Byte<E>]> = data
+        .chunks(TRANSITION_SIZE)
+        .map(|transition| &transition[..slot_size])
+        .collect();
+
+    for pair in slots.windows(2) {
+        let mut is_prefix_equal = Boolean::constant(true);
+        let mut difference = LinearCombination::zero();
+        for (previous, next) in pair[0].iter().zip(pair[1].iter()) {
+            let is_equal = Num::equals(cs, &previous.inner, &next.inner)?;
+            let is_first_difference = Boolean::and(cs, &is_prefix_equal, &is_equal.not())?;
+            let mut selector = LinearCombination::zero();
+            selector.add_assign_boolean_with_coeff(&is_first_difference, E::Fr::one());
+            let selector = selector.into_num(cs)?;
+
+            let delta = next.inner.sub(cs, &previous.inner)?;
+            difference.add_assign_number_with_coeff(&delta.mul(cs, &selector)?, E::Fr::one());
+            is_prefix_equal = Boolean::and(cs, &is_prefix_equal, &is_equal)?;
+        }
+        // not the same slot
+        Boolean::enforce_equal(cs, &is_prefix_equal, &Boolean::constant(false))?;
+
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+        difference.add_assign_constant(minus_one);
+        let difference = difference.into_num(cs)?;
+        enforce_fits_bytes(cs, &difference, 1, range_table)?;
+    }
+
+    Ok(())
+}
+
+// Read `len` bytes by dynamic index from bytes memory(O(1) per byte, see `ByteMemory`).
 // circuit arithmetic
 fn get_word_from_bytes<E: Engine, CS: ConstraintSystem<E>>(
     cs: &mut CS,
-    bytes: &Vec<Byte<E>>,
+    memory: &mut ByteMemory<E>,
     pos: &Num<E>,
+    len: usize,
 ) -> Result<Vec<Byte<E>>, SynthesisError> {
-    let mut result = Vec::with_capacity(33);
-    for index in 0..33 {
-        let zero = Num::zero();
+    let mut result = Vec::with_capacity(len);
+    for index in 0..len {
         let mut index_num = Num::Constant(E::Fr::from_str(&format!("{}", index)).unwrap());
         index_num = index_num.add(cs, pos)?;
-        let mut res_byte = Byte::from_num(cs, zero)?;
-        for (i, byte) in bytes.iter().enumerate() {
-            let i_num = Num::Constant(E::Fr::from_str(&format!("{}", i)).unwrap());
-            let flag = Num::equals(cs, &i_num, &index_num)?;
-            let mut flag = Num::from_boolean_is(flag);
-            flag = flag.mul(cs, &byte.inner)?;
-            res_byte.inner = res_byte.inner.add(cs, &flag)?;
+        result.push(memory.read(cs, &index_num)?);
+    }
+
+    Ok(result)
+}
+
+// Pack bytes into field elements, 31 bytes per element in big-endian order.
+// circuit arithmetic
+pub(crate) fn pack_bytes<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    bytes: &[Byte<E>],
+) -> Result<Vec<Num<E>>, SynthesisError> {
+    let shift = E::Fr::from_str("256").unwrap();
+    let mut result = Vec::with_capacity((bytes.len() + 30) / 31);
+    for chunk in bytes.chunks(31) {
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for byte in chunk.iter().rev() {
+            lc.add_assign_number_with_coeff(&byte.inner, coeff);
+            coeff.mul_assign(&shift);
         }
-        result.push(res_byte);
+        result.push(lc.into_num(cs)?);
     }
 
     Ok(result)
 }
+
+// Hash field elements with the Poseidon sponge. Native counterpart of `utils::poseidon_hash`.
+// circuit arithmetic
+pub(crate) fn circuit_poseidon_hash<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    input: &[Num<E>],
+) -> Result<Num<E>, SynthesisError> {
+    let params = PoseidonParams::<E, 2, 3>::default();
+    let output = CircuitGenericSponge::hash(cs, input, &params, None)?;
+
+    Ok(output[0])
+}
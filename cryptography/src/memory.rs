@@ -0,0 +1,153 @@
+use franklin_crypto::{
+    bellman::{
+        plonk::better_better_cs::cs::ConstraintSystem, Engine, Field, PrimeField, SynthesisError,
+    },
+    plonk::circuit::{allocated_num::Num, byte::Byte},
+};
+use rescue_poseidon::{CircuitGenericSponge, PoseidonParams};
+
+use crate::main_circuit::{allocate_and_prove_bytes, pack_bytes, RangeTable};
+
+// Read-only byte memory with constant cost dynamic reads.
+//
+// Selecting a byte by comparing the index with every cell costs O(n) gates per read.
+// Instead every read is recorded as an `(address, value)` pair and in the end we prove
+// that all the reads are contained in the `(index, byte)` cells with a logarithmic derivative
+// lookup argument:
+//
+//     sum_reads 1 / (gamma - (address + beta * value)) == sum_cells m_i / (gamma - (i + beta * byte_i))
+//
+// where `m_i` is the number of reads of the cell `i`. The challenges `gamma` and `beta` are squeezed
+// from the Poseidon sponge over the packed cells, read values and multiplicities, so the prover
+// can't choose the witness after seeing them. Addresses are not absorbed: they must be derived
+// in-circuit from constants and already read values.
+//
+// The cost of a read doesn't depend on the number of cells, every cell costs a multiplicity
+// lookup and 1 absorbed field element per 31 bytes. `test_memory_read_cost_against_baseline`
+// prints the gates of the reads of the default capacity with both approaches.
+pub struct ByteMemory<E: Engine> {
+    cells: Vec<Byte<E>>,
+    reads: Vec<(Num<E>, Byte<E>)>,
+}
+
+impl<E: Engine> ByteMemory<E> {
+    pub fn new(cells: Vec<Byte<E>>) -> Self {
+        Self {
+            cells,
+            reads: Vec::new(),
+        }
+    }
+
+    // Read byte by dynamic index.
+    // The value is allocated as a free witness, it is bound to the cell in `enforce_reads_consistency`.
+    pub fn read<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        address: &Num<E>,
+    ) -> Result<Byte<E>, SynthesisError> {
+        let value = address.get_value().map(|address| {
+            fe_to_usize::<E>(&address)
+                .and_then(|index| self.cells.get(index))
+                .and_then(|cell| cell.inner.get_value())
+                .unwrap_or(E::Fr::zero())
+        });
+
+        let byte = Byte {
+            inner: Num::alloc(cs, value)?,
+        };
+        self.reads.push((*address, byte));
+
+        Ok(byte)
+    }
+
+    // Prove that every read value is equal to the value of the cell with the same address.
+    // Must be called once, after all the reads.
+    pub fn enforce_reads_consistency<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        range_table: &RangeTable,
+    ) -> Result<(), SynthesisError> {
+        if self.reads.is_empty() {
+            return Ok(());
+        }
+
+        // A cell is read at most 33 times(the tag of a word of zeroes is read for each byte),
+        // so multiplicities fit into a byte.
+        let mut multiplicities = vec![Some(0u8); self.cells.len()];
+        for (address, _) in self.reads.iter() {
+            match address.get_value() {
+                Some(address) => {
+                    if let Some(m) =
+                        fe_to_usize::<E>(&address).and_then(|index| multiplicities.get_mut(index))
+                    {
+                        *m = m.map(|m| m.saturating_add(1));
+                    }
+                }
+                None => {
+                    multiplicities = vec![None; self.cells.len()];
+                    break;
+                }
+            }
+        }
+        let multiplicities =
+            allocate_and_prove_bytes(&multiplicities, self.cells.len(), cs, range_table, false)?;
+
+        let read_values: Vec<Byte<E>> = self.reads.iter().map(|(_, value)| *value).collect();
+        let mut transcript = pack_bytes(cs, &self.cells)?;
+        transcript.extend(pack_bytes(cs, &read_values)?);
+        transcript.extend(pack_bytes(cs, &multiplicities)?);
+
+        let params = PoseidonParams::<E, 2, 3>::default();
+        let challenges = CircuitGenericSponge::hash(cs, &transcript, &params, None)?;
+        let (gamma, beta) = (challenges[0], challenges[1]);
+
+        let mut reads_sum = Num::zero();
+        for (address, value) in self.reads.iter() {
+            let inverse = key_inverse(cs, &gamma, &beta, address, &value.inner)?;
+            reads_sum = reads_sum.add(cs, &inverse)?;
+        }
+
+        let mut cells_sum = Num::zero();
+        for (index, (cell, m)) in self.cells.iter().zip(multiplicities.iter()).enumerate() {
+            let address = Num::Constant(E::Fr::from_str(&format!("{}", index)).unwrap());
+            let inverse = key_inverse(cs, &gamma, &beta, &address, &cell.inner)?;
+            let term = inverse.mul(cs, &m.inner)?;
+            cells_sum = cells_sum.add(cs, &term)?;
+        }
+
+        reads_sum.enforce_equal(cs, &cells_sum)
+    }
+}
+
+// Allocate 1 / (gamma - (address + beta * value)) and prove it by `denominator * inverse == 1`.
+fn key_inverse<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    gamma: &Num<E>,
+    beta: &Num<E>,
+    address: &Num<E>,
+    value: &Num<E>,
+) -> Result<Num<E>, SynthesisError> {
+    let key = beta.mul(cs, value)?;
+    let key = key.add(cs, address)?;
+    let denominator = gamma.sub(cs, &key)?;
+
+    let inverse = Num::alloc(
+        cs,
+        denominator
+            .get_value()
+            .map(|denominator| denominator.inverse().unwrap_or(E::Fr::zero())),
+    )?;
+    let product = denominator.mul(cs, &inverse)?;
+    product.enforce_equal(cs, &Num::one())?;
+
+    Ok(inverse)
+}
+
+pub(crate) fn fe_to_usize<E: Engine>(value: &E::Fr) -> Option<usize> {
+    let repr = value.into_repr();
+    let limbs = repr.as_ref();
+    if limbs[1..].iter().any(|limb| *limb != 0) {
+        return None;
+    }
+    usize::try_from(limbs[0]).ok()
+}
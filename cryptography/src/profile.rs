@@ -0,0 +1,154 @@
+use std::{collections::HashSet, fmt};
+
+use franklin_crypto::bellman::plonk::better_better_cs::cs::PolyIdentifier;
+
+use crate::{
+    config::CircuitConfig, debug::Regions, error::Error, main_circuit::CompressionCircuit,
+    CompressionSetupAssembly,
+};
+
+// Cost of one kind of region, e.g. all the `word N leading-zero branch N` regions together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionCost {
+    pub region: String,
+    pub instances: usize,
+    pub gates: usize,
+    pub lookups: usize,
+    // Variables first used by a gate of the region.
+    pub variables: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub config: CircuitConfig,
+    // In the order of the first appearance in the circuit.
+    pub regions: Vec<RegionCost>,
+    pub total: RegionCost,
+    // Rows of the lookup tables, the domain must fit them as well as the gates.
+    pub table_rows: usize,
+}
+
+// Synthesize the circuit without a witness and split the gates by the labeled regions.
+// Numbers in the labels are replaced by `N`, so the regions of every word and branch are summed up.
+pub fn profile(config: CircuitConfig) -> Result<Profile, Error> {
+    let circuit = CompressionCircuit::empty(config);
+    let mut assembly = CompressionSetupAssembly::new();
+    let mut regions = Regions::default();
+    circuit.synthesize_with_regions(&mut assembly, &mut regions)?;
+
+    let gates = assembly.num_aux_gates;
+    let is_lookup = |gate: usize| {
+        assembly
+            .table_selectors
+            .values()
+            .filter(|selector| selector.get(gate).unwrap_or(false))
+            .count()
+    };
+    let columns: Vec<_> = (0..4)
+        .filter_map(|column| {
+            assembly
+                .aux_storage
+                .state_map
+                .get(&PolyIdentifier::VariablesPolynomial(column))
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut result: Vec<RegionCost> = Vec::new();
+    let mut total = RegionCost {
+        region: "total".to_owned(),
+        ..Default::default()
+    };
+    for (span, label) in regions.spans(gates) {
+        let region = collapse_numbers(label);
+        let index = match result.iter().position(|cost| cost.region == region) {
+            Some(index) => index,
+            None => {
+                result.push(RegionCost {
+                    region,
+                    ..Default::default()
+                });
+                result.len() - 1
+            }
+        };
+
+        let cost = &mut result[index];
+        cost.instances += 1;
+        cost.gates += span.len();
+        for gate in span {
+            cost.lookups += is_lookup(gate);
+            for column in columns.iter() {
+                if let Some(variable) = column.get(gate) {
+                    if seen.insert(*variable) {
+                        cost.variables += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    for cost in result.iter() {
+        total.instances += cost.instances;
+        total.gates += cost.gates;
+        total.lookups += cost.lookups;
+        total.variables += cost.variables;
+    }
+
+    let table_rows = assembly
+        .table_selectors
+        .keys()
+        .map(|name| assembly.get_table(name).map(|table| table.size()))
+        .sum::<Result<usize, _>>()?;
+
+    Ok(Profile {
+        config,
+        regions: result,
+        total,
+        table_rows,
+    })
+}
+
+fn collapse_numbers(label: &str) -> String {
+    let mut result = String::with_capacity(label.len());
+    let mut in_number = false;
+    for char in label.chars() {
+        if char.is_ascii_digit() {
+            if !in_number {
+                result.push('N');
+            }
+            in_number = true;
+        } else {
+            result.push(char);
+            in_number = false;
+        }
+    }
+    result
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .regions
+            .iter()
+            .map(|cost| cost.region.len())
+            .max()
+            .unwrap_or(0)
+            .max("region".len());
+
+        writeln!(f, "capacity: {} transitions", self.config.capacity)?;
+        writeln!(f, "table rows: {}", self.table_rows)?;
+        writeln!(
+            f,
+            "{:<width$} {:>9} {:>9} {:>9} {:>9}",
+            "region", "instances", "gates", "lookups", "variables"
+        )?;
+        for cost in self.regions.iter().chain([&self.total]) {
+            writeln!(
+                f,
+                "{:<width$} {:>9} {:>9} {:>9} {:>9}",
+                cost.region, cost.instances, cost.gates, cost.lookups, cost.variables
+            )?;
+        }
+        Ok(())
+    }
+}
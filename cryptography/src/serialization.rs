@@ -0,0 +1,186 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use franklin_crypto::bellman::{
+    compact_bn256::Bn256,
+    plonk::better_better_cs::{cs::Setup, proof::Proof, setup::VerificationKey},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{error::Error, main_circuit::CompressionCircuit};
+
+pub type CompressionProof = Proof<Bn256, CompressionCircuit<Bn256>>;
+pub type CompressionVerificationKey = VerificationKey<Bn256, CompressionCircuit<Bn256>>;
+pub type CompressionSetup = Setup<Bn256, CompressionCircuit<Bn256>>;
+
+// Encoding of proofs and verification keys in files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    // Compact bellman encoding: big-endian field elements and uncompressed curve points.
+    Binary,
+    // Human readable encoding with hex field elements.
+    Json,
+}
+
+impl Format {
+    // `.json` files are JSON, everything else is binary.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Format::Json,
+            _ => Format::Binary,
+        }
+    }
+}
+
+pub fn write_proof<W: Write>(
+    proof: &CompressionProof,
+    writer: W,
+    format: Format,
+) -> io::Result<()> {
+    match format {
+        Format::Binary => proof.write(writer),
+        Format::Json => write_json(proof, writer),
+    }
+}
+
+pub fn read_proof<R: Read>(reader: R, format: Format) -> io::Result<CompressionProof> {
+    match format {
+        Format::Binary => CompressionProof::read(reader),
+        Format::Json => read_json(reader),
+    }
+}
+
+pub fn write_vk<W: Write>(
+    vk: &CompressionVerificationKey,
+    writer: W,
+    format: Format,
+) -> io::Result<()> {
+    match format {
+        Format::Binary => vk.write(writer),
+        Format::Json => write_json(vk, writer),
+    }
+}
+
+pub fn read_vk<R: Read>(reader: R, format: Format) -> io::Result<CompressionVerificationKey> {
+    match format {
+        Format::Binary => CompressionVerificationKey::read(reader),
+        Format::Json => read_json(reader),
+    }
+}
+
+// bellman serializes a field element as the 4 little-endian u64 limbs of its representation,
+// they are written as a 0x prefixed big-endian hex string instead.
+fn write_json<T: Serialize, W: Write>(value: &T, writer: W) -> io::Result<()> {
+    let value = limbs_to_hex(serde_json::to_value(value)?);
+    serde_json::to_writer_pretty(writer, &value).map_err(io::Error::from)
+}
+
+fn read_json<T: DeserializeOwned, R: Read>(reader: R) -> io::Result<T> {
+    let value = hex_to_limbs(serde_json::from_reader(reader)?)?;
+    serde_json::from_value(value).map_err(io::Error::from)
+}
+
+fn limbs_to_hex(value: Value) -> Value {
+    match value {
+        Value::Array(values) => {
+            let limbs: Vec<u64> = values.iter().filter_map(Value::as_u64).collect();
+            if values.len() == 4 && limbs.len() == 4 {
+                let hex: String = limbs
+                    .iter()
+                    .rev()
+                    .map(|limb| format!("{limb:016x}"))
+                    .collect();
+                Value::String(format!("0x{hex}"))
+            } else {
+                Value::Array(values.into_iter().map(limbs_to_hex).collect())
+            }
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name, limbs_to_hex(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+fn hex_to_limbs(value: Value) -> io::Result<Value> {
+    Ok(match value {
+        Value::String(hex) => {
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid field element");
+            let digits = hex.strip_prefix("0x").ok_or_else(invalid)?;
+            if digits.len() != 64 || !digits.is_ascii() {
+                return Err(invalid());
+            }
+            let mut limbs = Vec::with_capacity(4);
+            for index in (0..4).rev() {
+                let limb = &digits[16 * index..16 * (index + 1)];
+                limbs.push(Value::from(
+                    u64::from_str_radix(limb, 16).map_err(|_| invalid())?,
+                ));
+            }
+            Value::Array(limbs)
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(hex_to_limbs)
+                .collect::<io::Result<_>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| Ok((name, hex_to_limbs(value)?)))
+                .collect::<io::Result<_>>()?,
+        ),
+        value => value,
+    })
+}
+
+// Save the proof to the file, the format is chosen by the extension.
+pub fn save_proof<P: AsRef<Path>>(proof: &CompressionProof, path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_proof(proof, &mut writer, Format::from_path(path))?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load_proof<P: AsRef<Path>>(path: P) -> Result<CompressionProof, Error> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+    Ok(read_proof(reader, Format::from_path(path))?)
+}
+
+// Save the verification key to the file, the format is chosen by the extension.
+pub fn save_vk<P: AsRef<Path>>(vk: &CompressionVerificationKey, path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_vk(vk, &mut writer, Format::from_path(path))?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load_vk<P: AsRef<Path>>(path: P) -> Result<CompressionVerificationKey, Error> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+    Ok(read_vk(reader, Format::from_path(path))?)
+}
+
+// The prover setup is large, so it is always stored in the binary format.
+pub fn save_setup<P: AsRef<Path>>(setup: &CompressionSetup, path: P) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    setup.write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load_setup<P: AsRef<Path>>(path: P) -> Result<CompressionSetup, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(CompressionSetup::read(reader)?)
+}
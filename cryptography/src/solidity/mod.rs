@@ -0,0 +1,243 @@
+use compression::{sha3, sha3::Digest};
+use franklin_crypto::bellman::{
+    compact_bn256::{Fq2, Fr, G1Affine, G2Affine},
+    CurveAffine, Field, PrimeField, PrimeFieldRepr,
+};
+
+use crate::serialization::{CompressionProof, CompressionVerificationKey};
+
+// Solidity verifier for the proofs of the circuit.
+//
+// The template checks the PLONK variant we prove with: 4 state columns with access to d(X * omega)
+// (Width4MainGateWithDNext), one lookup argument over a width 3 table and RollingKeccakTranscript.
+// The generator only fills in the key, so it refuses keys of any other shape.
+const VERIFIER_TEMPLATE: &str = include_str!("verifier.sol");
+
+const STATE_WIDTH: usize = 4;
+const MAIN_GATE_SETUP_POLYNOMIALS: usize = 7;
+const LOOKUP_TABLE_COLUMNS: usize = 4;
+
+// Number of 32 byte words in `serialize_proof`, `SERIALIZED_PROOF_LENGTH` in the template.
+pub const SERIALIZED_PROOF_LENGTH: usize = 43;
+
+// Emit the verifier contract with the verification key hardcoded.
+pub fn generate_verifier(vk: &CompressionVerificationKey) -> String {
+    assert_eq!(vk.state_width, STATE_WIDTH, "unsupported state width");
+    assert_eq!(
+        vk.num_witness_polys, 0,
+        "witness polynomials are not supported"
+    );
+    assert_eq!(
+        vk.gate_setup_commitments.len(),
+        MAIN_GATE_SETUP_POLYNOMIALS,
+        "only the main gate is supported"
+    );
+    assert!(
+        vk.gate_selectors_commitments.is_empty(),
+        "custom gates are not supported"
+    );
+    assert_eq!(vk.permutation_commitments.len(), STATE_WIDTH);
+    assert!(
+        vk.total_lookup_entries_length > 0,
+        "the lookup argument is expected"
+    );
+    assert_eq!(vk.lookup_tables_commitments.len(), LOOKUP_TABLE_COLUMNS);
+
+    let domain_size = vk.n + 1;
+    assert!(domain_size.is_power_of_two());
+
+    let mut key = String::new();
+    for (index, point) in vk.gate_setup_commitments.iter().enumerate() {
+        key += &g1_assignment(&format!("vk.gateSetup[{index}]"), point);
+    }
+    for (index, point) in vk.permutation_commitments.iter().enumerate() {
+        key += &g1_assignment(&format!("vk.permutation[{index}]"), point);
+    }
+    for (index, point) in vk.lookup_tables_commitments.iter().enumerate() {
+        key += &g1_assignment(&format!("vk.lookupTables[{index}]"), point);
+    }
+    key += &g1_assignment(
+        "vk.lookupSelector",
+        vk.lookup_selector_commitment
+            .as_ref()
+            .expect("lookup selector commitment"),
+    );
+    key += &g1_assignment(
+        "vk.lookupTableType",
+        vk.lookup_table_type_commitment
+            .as_ref()
+            .expect("lookup table type commitment"),
+    );
+    for (index, non_residue) in vk.non_residues.iter().enumerate() {
+        key += &format!(
+            "        vk.nonResidues[{index}] = {};\n",
+            fe_to_hex(non_residue)
+        );
+    }
+    key += &g2_assignment("vk.g2Generator", &vk.g2_elements[0]);
+    key += &g2_assignment("vk.g2X", &vk.g2_elements[1]);
+
+    VERIFIER_TEMPLATE
+        .replace("{{DOMAIN_SIZE}}", &domain_size.to_string())
+        .replace("{{OMEGA}}", &fe_to_hex(&omega(domain_size)))
+        .replace("{{NUM_INPUTS}}", &vk.num_inputs.to_string())
+        .replace("{{VERIFICATION_KEY}}\n", &key)
+}
+
+// Flatten the proof into the `serializedProof` argument of the verifier,
+// in the order of `deserializeProof`.
+pub fn serialize_proof(proof: &CompressionProof) -> Vec<[u8; 32]> {
+    let mut words = Vec::with_capacity(SERIALIZED_PROOF_LENGTH);
+
+    for point in proof.state_polys_commitments.iter() {
+        push_g1(&mut words, point);
+    }
+    push_g1(&mut words, &proof.copy_permutation_grand_product_commitment);
+    push_g1(
+        &mut words,
+        proof
+            .lookup_s_poly_commitment
+            .as_ref()
+            .expect("lookup proof"),
+    );
+    push_g1(
+        &mut words,
+        proof
+            .lookup_grand_product_commitment
+            .as_ref()
+            .expect("lookup proof"),
+    );
+    for point in proof.quotient_poly_parts_commitments.iter() {
+        push_g1(&mut words, point);
+    }
+
+    let values = proof
+        .state_polys_openings_at_z
+        .iter()
+        .chain(
+            proof
+                .state_polys_openings_at_dilations
+                .iter()
+                .map(|(_, _, value)| value),
+        )
+        .chain(proof.copy_permutation_polys_openings_at_z.iter())
+        .chain([
+            &proof.copy_permutation_grand_product_opening_at_z_omega,
+            proof
+                .lookup_s_poly_opening_at_z_omega
+                .as_ref()
+                .expect("lookup proof"),
+            proof
+                .lookup_grand_product_opening_at_z_omega
+                .as_ref()
+                .expect("lookup proof"),
+            proof
+                .lookup_t_poly_opening_at_z
+                .as_ref()
+                .expect("lookup proof"),
+            proof
+                .lookup_t_poly_opening_at_z_omega
+                .as_ref()
+                .expect("lookup proof"),
+            proof
+                .lookup_selector_poly_opening_at_z
+                .as_ref()
+                .expect("lookup proof"),
+            proof
+                .lookup_table_type_poly_opening_at_z
+                .as_ref()
+                .expect("lookup proof"),
+            &proof.quotient_poly_opening_at_z,
+            &proof.linearization_poly_opening_at_z,
+        ]);
+    for value in values {
+        words.push(fe_to_bytes(value));
+    }
+
+    push_g1(&mut words, &proof.opening_proof_at_z);
+    push_g1(&mut words, &proof.opening_proof_at_z_omega);
+
+    assert_eq!(
+        words.len(),
+        SERIALIZED_PROOF_LENGTH,
+        "unexpected proof shape"
+    );
+    words
+}
+
+// ABI encoded call of `verify(uint256[] publicInputs, uint256[] serializedProof)`.
+pub fn calldata(proof: &CompressionProof) -> Vec<u8> {
+    let inputs: Vec<[u8; 32]> = proof.inputs.iter().map(fe_to_bytes).collect();
+    let serialized_proof = serialize_proof(proof);
+
+    let mut calldata = sha3::Keccak256::digest(b"verify(uint256[],uint256[])")[..4].to_vec();
+    // offsets of the dynamic arrays from the start of the arguments
+    calldata.extend(usize_to_word(2 * 32));
+    calldata.extend(usize_to_word((3 + inputs.len()) * 32));
+    for array in [inputs, serialized_proof] {
+        calldata.extend(usize_to_word(array.len()));
+        for word in array {
+            calldata.extend(word);
+        }
+    }
+
+    calldata
+}
+
+fn push_g1(words: &mut Vec<[u8; 32]>, point: &G1Affine) {
+    let (x, y) = point.into_xy_unchecked();
+    words.push(fe_to_bytes(&x));
+    words.push(fe_to_bytes(&y));
+}
+
+fn g1_assignment(name: &str, point: &G1Affine) -> String {
+    let (x, y) = point.into_xy_unchecked();
+    format!(
+        "        {name} = G1Point({}, {});\n",
+        fe_to_hex(&x),
+        fe_to_hex(&y)
+    )
+}
+
+// The precompile expects the imaginary part first.
+fn g2_assignment(name: &str, point: &G2Affine) -> String {
+    let (x, y): (Fq2, Fq2) = point.into_xy_unchecked();
+    format!(
+        "        {name} = [uint256({}), {}, {}, {}];\n",
+        fe_to_hex(&x.c1),
+        fe_to_hex(&x.c0),
+        fe_to_hex(&y.c1),
+        fe_to_hex(&y.c0)
+    )
+}
+
+// Generator of the multiplicative subgroup of the given power of two size.
+fn omega(domain_size: usize) -> Fr {
+    let mut omega = Fr::root_of_unity();
+    for _ in domain_size.trailing_zeros()..Fr::S {
+        omega.square();
+    }
+    omega
+}
+
+pub(crate) fn fe_to_bytes<F: PrimeField>(fe: &F) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    fe.into_repr()
+        .write_be(&mut bytes[..])
+        .expect("field element fits into 32 bytes");
+    bytes
+}
+
+fn fe_to_hex<F: PrimeField>(fe: &F) -> String {
+    let hex: String = fe_to_bytes(fe)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("0x{hex}")
+}
+
+fn usize_to_word(value: usize) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
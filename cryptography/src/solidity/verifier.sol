@@ -0,0 +1,548 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+// Verifier of the CompressionCircuit proofs.
+// Generated from the verification key by `cryptography::solidity::generate_verifier`, do not edit.
+//
+// PLONK over BN254 with Width4MainGateWithDNext, a single lookup argument
+// and RollingKeccakTranscript, the same checks as the bellman `better_better_cs` verifier.
+contract CompressionVerifier {
+    uint256 constant Q_MOD = 21888242871839275222246405745257275088696311157297823662689037894645226208583;
+    uint256 constant R_MOD = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    uint256 constant FR_MASK = 0x1fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff;
+
+    uint256 constant DOMAIN_SIZE = {{DOMAIN_SIZE}};
+    uint256 constant OMEGA = {{OMEGA}};
+    uint256 constant NUM_INPUTS = {{NUM_INPUTS}};
+    uint256 constant SERIALIZED_PROOF_LENGTH = 43;
+
+    struct G1Point {
+        uint256 x;
+        uint256 y;
+    }
+
+    // G2 points are stored in the precompile order: [x.c1, x.c0, y.c1, y.c0].
+    struct VerificationKey {
+        // q_a, q_b, q_c, q_d, q_m, q_const, q_d_next
+        G1Point[7] gateSetup;
+        G1Point[4] permutation;
+        G1Point[4] lookupTables;
+        G1Point lookupSelector;
+        G1Point lookupTableType;
+        uint256[3] nonResidues;
+        uint256[4] g2Generator;
+        uint256[4] g2X;
+    }
+
+    struct Proof {
+        uint256[] inputs;
+        G1Point[4] state;
+        G1Point copyPermutationGrandProduct;
+        G1Point lookupS;
+        G1Point lookupGrandProduct;
+        G1Point[4] quotientParts;
+        uint256[4] stateAtZ;
+        uint256 dAtZOmega;
+        uint256[3] sigmaAtZ;
+        uint256 copyPermutationGrandProductAtZOmega;
+        uint256 lookupSAtZOmega;
+        uint256 lookupGrandProductAtZOmega;
+        uint256 lookupTAtZ;
+        uint256 lookupTAtZOmega;
+        uint256 lookupSelectorAtZ;
+        uint256 lookupTableTypeAtZ;
+        uint256 quotientAtZ;
+        uint256 linearizationAtZ;
+        G1Point openingAtZ;
+        G1Point openingAtZOmega;
+    }
+
+    struct State {
+        uint256 eta;
+        uint256 beta;
+        uint256 gamma;
+        uint256 betaLookup;
+        uint256 gammaLookup;
+        uint256 alpha;
+        uint256 z;
+        uint256 v;
+        uint256 u;
+        // alpha^1, alpha^2 for the copy permutation and alpha^3, alpha^4, alpha^5 for the lookup
+        uint256[5] alphas;
+        uint256 zInDomainSize;
+        uint256 l0AtZ;
+        uint256 lLastAtZ;
+        uint256 zMinusLastOmega;
+        uint256 betaPlusOne;
+        uint256 gammaBeta;
+    }
+
+    struct Transcript {
+        bytes32 state0;
+        bytes32 state1;
+        uint32 counter;
+    }
+
+    function verificationKey() internal pure returns (VerificationKey memory vk) {
+{{VERIFICATION_KEY}}
+    }
+
+    function verify(uint256[] calldata publicInputs, uint256[] calldata serializedProof)
+        external
+        view
+        returns (bool)
+    {
+        VerificationKey memory vk = verificationKey();
+        Proof memory proof = deserializeProof(publicInputs, serializedProof);
+        State memory state = computeChallenges(proof);
+
+        if (!verifyQuotientAtZ(proof, state)) {
+            return false;
+        }
+
+        return verifyOpenings(vk, proof, state);
+    }
+
+    function deserializeProof(uint256[] calldata publicInputs, uint256[] calldata serialized)
+        internal
+        pure
+        returns (Proof memory proof)
+    {
+        require(publicInputs.length == NUM_INPUTS, "wrong number of public inputs");
+        require(serialized.length == SERIALIZED_PROOF_LENGTH, "wrong proof length");
+
+        proof.inputs = new uint256[](publicInputs.length);
+        for (uint256 i = 0; i < publicInputs.length; i++) {
+            proof.inputs[i] = checkedFr(publicInputs[i]);
+        }
+
+        uint256 j = 0;
+        for (uint256 i = 0; i < 4; i++) {
+            proof.state[i] = checkedG1(serialized[j], serialized[j + 1]);
+            j += 2;
+        }
+        proof.copyPermutationGrandProduct = checkedG1(serialized[j], serialized[j + 1]);
+        j += 2;
+        proof.lookupS = checkedG1(serialized[j], serialized[j + 1]);
+        j += 2;
+        proof.lookupGrandProduct = checkedG1(serialized[j], serialized[j + 1]);
+        j += 2;
+        for (uint256 i = 0; i < 4; i++) {
+            proof.quotientParts[i] = checkedG1(serialized[j], serialized[j + 1]);
+            j += 2;
+        }
+        for (uint256 i = 0; i < 4; i++) {
+            proof.stateAtZ[i] = checkedFr(serialized[j++]);
+        }
+        proof.dAtZOmega = checkedFr(serialized[j++]);
+        for (uint256 i = 0; i < 3; i++) {
+            proof.sigmaAtZ[i] = checkedFr(serialized[j++]);
+        }
+        proof.copyPermutationGrandProductAtZOmega = checkedFr(serialized[j++]);
+        proof.lookupSAtZOmega = checkedFr(serialized[j++]);
+        proof.lookupGrandProductAtZOmega = checkedFr(serialized[j++]);
+        proof.lookupTAtZ = checkedFr(serialized[j++]);
+        proof.lookupTAtZOmega = checkedFr(serialized[j++]);
+        proof.lookupSelectorAtZ = checkedFr(serialized[j++]);
+        proof.lookupTableTypeAtZ = checkedFr(serialized[j++]);
+        proof.quotientAtZ = checkedFr(serialized[j++]);
+        proof.linearizationAtZ = checkedFr(serialized[j++]);
+        proof.openingAtZ = checkedG1(serialized[j], serialized[j + 1]);
+        j += 2;
+        proof.openingAtZOmega = checkedG1(serialized[j], serialized[j + 1]);
+    }
+
+    // The order of the transcript updates must match the prover.
+    function computeChallenges(Proof memory proof) internal view returns (State memory state) {
+        Transcript memory transcript;
+
+        for (uint256 i = 0; i < proof.inputs.length; i++) {
+            updateTranscript(transcript, proof.inputs[i]);
+        }
+        for (uint256 i = 0; i < 4; i++) {
+            updateTranscriptG1(transcript, proof.state[i]);
+        }
+
+        state.eta = getChallenge(transcript);
+        updateTranscriptG1(transcript, proof.lookupS);
+
+        state.beta = getChallenge(transcript);
+        state.gamma = getChallenge(transcript);
+        updateTranscriptG1(transcript, proof.copyPermutationGrandProduct);
+
+        state.betaLookup = getChallenge(transcript);
+        state.gammaLookup = getChallenge(transcript);
+        updateTranscriptG1(transcript, proof.lookupGrandProduct);
+
+        state.alpha = getChallenge(transcript);
+        for (uint256 i = 0; i < 4; i++) {
+            updateTranscriptG1(transcript, proof.quotientParts[i]);
+        }
+
+        state.z = getChallenge(transcript);
+        updateTranscript(transcript, proof.quotientAtZ);
+        for (uint256 i = 0; i < 4; i++) {
+            updateTranscript(transcript, proof.stateAtZ[i]);
+        }
+        updateTranscript(transcript, proof.dAtZOmega);
+        for (uint256 i = 0; i < 3; i++) {
+            updateTranscript(transcript, proof.sigmaAtZ[i]);
+        }
+        updateTranscript(transcript, proof.copyPermutationGrandProductAtZOmega);
+        // the lookup openings at z, then at z * omega, as the bellman verifier absorbs them
+        updateTranscript(transcript, proof.lookupTAtZ);
+        updateTranscript(transcript, proof.lookupSelectorAtZ);
+        updateTranscript(transcript, proof.lookupTableTypeAtZ);
+        updateTranscript(transcript, proof.lookupSAtZOmega);
+        updateTranscript(transcript, proof.lookupGrandProductAtZOmega);
+        updateTranscript(transcript, proof.lookupTAtZOmega);
+        updateTranscript(transcript, proof.linearizationAtZ);
+
+        state.v = getChallenge(transcript);
+        updateTranscriptG1(transcript, proof.openingAtZ);
+        updateTranscriptG1(transcript, proof.openingAtZOmega);
+
+        state.u = getChallenge(transcript);
+
+        uint256 currentAlpha = 1;
+        for (uint256 i = 0; i < 5; i++) {
+            currentAlpha = mulmod(currentAlpha, state.alpha, R_MOD);
+            state.alphas[i] = currentAlpha;
+        }
+
+        state.zInDomainSize = fpow(state.z, DOMAIN_SIZE);
+        state.l0AtZ = lagrangeAtZ(state, 0);
+        state.lLastAtZ = lagrangeAtZ(state, DOMAIN_SIZE - 1);
+        state.zMinusLastOmega = fsub(state.z, fpow(OMEGA, DOMAIN_SIZE - 1));
+        state.betaPlusOne = addmod(state.betaLookup, 1, R_MOD);
+        state.gammaBeta = mulmod(state.gammaLookup, state.betaPlusOne, R_MOD);
+    }
+
+    // t(z) * (z^n - 1) == r(z) + PI(z) + the terms of the quotient which are not in the linearization.
+    function verifyQuotientAtZ(Proof memory proof, State memory state) internal view returns (bool)
+    {
+        uint256 lhs = mulmod(proof.quotientAtZ, fsub(state.zInDomainSize, 1), R_MOD);
+
+        uint256 rhs = proof.linearizationAtZ;
+        for (uint256 i = 0; i < proof.inputs.length; i++) {
+            rhs = addmod(rhs, mulmod(lagrangeAtZ(state, i), proof.inputs[i], R_MOD), R_MOD);
+        }
+
+        // copy permutation
+        uint256 zPart = proof.copyPermutationGrandProductAtZOmega;
+        for (uint256 i = 0; i < 3; i++) {
+            uint256 tmp = mulmod(proof.sigmaAtZ[i], state.beta, R_MOD);
+            tmp = addmod(tmp, state.gamma, R_MOD);
+            tmp = addmod(tmp, proof.stateAtZ[i], R_MOD);
+            zPart = mulmod(zPart, tmp, R_MOD);
+        }
+        zPart = mulmod(zPart, addmod(proof.stateAtZ[3], state.gamma, R_MOD), R_MOD);
+        zPart = mulmod(zPart, state.alphas[0], R_MOD);
+        rhs = fsub(rhs, zPart);
+        rhs = fsub(rhs, mulmod(state.l0AtZ, state.alphas[1], R_MOD));
+
+        // lookup
+        uint256 lookupPart = mulmod(proof.lookupSAtZOmega, state.betaLookup, R_MOD);
+        lookupPart = addmod(lookupPart, state.gammaBeta, R_MOD);
+        lookupPart = mulmod(lookupPart, proof.lookupGrandProductAtZOmega, R_MOD);
+        lookupPart = mulmod(lookupPart, state.alphas[2], R_MOD);
+        lookupPart = mulmod(lookupPart, state.zMinusLastOmega, R_MOD);
+        rhs = addmod(rhs, lookupPart, R_MOD);
+        rhs = fsub(rhs, mulmod(state.l0AtZ, state.alphas[3], R_MOD));
+
+        uint256 expected = fpow(state.gammaBeta, DOMAIN_SIZE - 1);
+        uint256 lastPart = mulmod(state.lLastAtZ, expected, R_MOD);
+        rhs = fsub(rhs, mulmod(lastPart, state.alphas[4], R_MOD));
+
+        return lhs == rhs;
+    }
+
+    // Commitment to the linearization polynomial r(X) from the commitments and openings.
+    function linearizationCommitment(VerificationKey memory vk, Proof memory proof, State memory state)
+        internal
+        view
+        returns (G1Point memory r)
+    {
+        // main gate
+        r = pointMul(vk.gateSetup[0], proof.stateAtZ[0]);
+        r = pointAdd(r, pointMul(vk.gateSetup[1], proof.stateAtZ[1]));
+        r = pointAdd(r, pointMul(vk.gateSetup[2], proof.stateAtZ[2]));
+        r = pointAdd(r, pointMul(vk.gateSetup[3], proof.stateAtZ[3]));
+        r = pointAdd(r, pointMul(vk.gateSetup[4], mulmod(proof.stateAtZ[0], proof.stateAtZ[1], R_MOD)));
+        r = pointAdd(r, vk.gateSetup[5]);
+        r = pointAdd(r, pointMul(vk.gateSetup[6], proof.dAtZOmega));
+
+        r = pointAdd(r, copyPermutationLinearization(vk, proof, state));
+        r = pointAdd(r, lookupLinearization(proof, state));
+    }
+
+    function copyPermutationLinearization(VerificationKey memory vk, Proof memory proof, State memory state)
+        internal
+        view
+        returns (G1Point memory result)
+    {
+        uint256 grandProductFactor = mulmod(state.z, state.beta, R_MOD);
+        grandProductFactor = addmod(grandProductFactor, state.gamma, R_MOD);
+        grandProductFactor = addmod(grandProductFactor, proof.stateAtZ[0], R_MOD);
+        for (uint256 i = 0; i < 3; i++) {
+            uint256 tmp = mulmod(state.z, vk.nonResidues[i], R_MOD);
+            tmp = mulmod(tmp, state.beta, R_MOD);
+            tmp = addmod(tmp, state.gamma, R_MOD);
+            tmp = addmod(tmp, proof.stateAtZ[i + 1], R_MOD);
+            grandProductFactor = mulmod(grandProductFactor, tmp, R_MOD);
+        }
+        grandProductFactor = mulmod(grandProductFactor, state.alphas[0], R_MOD);
+        grandProductFactor = addmod(grandProductFactor, mulmod(state.l0AtZ, state.alphas[1], R_MOD), R_MOD);
+
+        uint256 lastSigmaFactor = 1;
+        for (uint256 i = 0; i < 3; i++) {
+            uint256 tmp = mulmod(proof.sigmaAtZ[i], state.beta, R_MOD);
+            tmp = addmod(tmp, state.gamma, R_MOD);
+            tmp = addmod(tmp, proof.stateAtZ[i], R_MOD);
+            lastSigmaFactor = mulmod(lastSigmaFactor, tmp, R_MOD);
+        }
+        lastSigmaFactor = mulmod(lastSigmaFactor, state.beta, R_MOD);
+        lastSigmaFactor = mulmod(lastSigmaFactor, proof.copyPermutationGrandProductAtZOmega, R_MOD);
+        lastSigmaFactor = mulmod(lastSigmaFactor, state.alphas[0], R_MOD);
+
+        result = pointMul(proof.copyPermutationGrandProduct, grandProductFactor);
+        result = pointSub(result, pointMul(vk.permutation[3], lastSigmaFactor));
+    }
+
+    function lookupLinearization(Proof memory proof, State memory state)
+        internal
+        view
+        returns (G1Point memory result)
+    {
+        uint256 sFactor = mulmod(proof.lookupGrandProductAtZOmega, state.alphas[2], R_MOD);
+        sFactor = mulmod(sFactor, state.zMinusLastOmega, R_MOD);
+
+        // f(z) = selector(z) * (a(z) + eta * b(z) + eta^2 * c(z) + eta^3 * table_type(z))
+        uint256 f = proof.lookupTableTypeAtZ;
+        f = addmod(mulmod(f, state.eta, R_MOD), proof.stateAtZ[2], R_MOD);
+        f = addmod(mulmod(f, state.eta, R_MOD), proof.stateAtZ[1], R_MOD);
+        f = addmod(mulmod(f, state.eta, R_MOD), proof.stateAtZ[0], R_MOD);
+        f = mulmod(f, proof.lookupSelectorAtZ, R_MOD);
+
+        uint256 grandProductFactor = mulmod(proof.lookupTAtZOmega, state.betaLookup, R_MOD);
+        grandProductFactor = addmod(grandProductFactor, proof.lookupTAtZ, R_MOD);
+        grandProductFactor = addmod(grandProductFactor, state.gammaBeta, R_MOD);
+        grandProductFactor = mulmod(grandProductFactor, addmod(f, state.gammaLookup, R_MOD), R_MOD);
+        grandProductFactor = mulmod(grandProductFactor, state.betaPlusOne, R_MOD);
+        grandProductFactor = mulmod(grandProductFactor, state.alphas[2], R_MOD);
+        grandProductFactor = mulmod(grandProductFactor, state.zMinusLastOmega, R_MOD);
+        grandProductFactor = fsub(0, grandProductFactor);
+        grandProductFactor = addmod(grandProductFactor, mulmod(state.l0AtZ, state.alphas[3], R_MOD), R_MOD);
+        grandProductFactor = addmod(grandProductFactor, mulmod(state.lLastAtZ, state.alphas[4], R_MOD), R_MOD);
+
+        result = pointMul(proof.lookupS, sFactor);
+        result = pointAdd(result, pointMul(proof.lookupGrandProduct, grandProductFactor));
+    }
+
+    // t(X) = t_0(X) + eta * t_1(X) + eta^2 * t_2(X) + eta^3 * t_3(X)
+    function lookupTableCommitment(VerificationKey memory vk, State memory state)
+        internal
+        view
+        returns (G1Point memory result)
+    {
+        result = vk.lookupTables[3];
+        for (uint256 i = 3; i > 0; i--) {
+            result = pointAdd(pointMul(result, state.eta), vk.lookupTables[i - 1]);
+        }
+    }
+
+    // Aggregate all the openings with powers of v (and u for the openings at z * omega)
+    // in the same order as the prover and check them with one pairing.
+    function verifyOpenings(VerificationKey memory vk, Proof memory proof, State memory state)
+        internal
+        view
+        returns (bool)
+    {
+        G1Point memory lookupTable = lookupTableCommitment(vk, state);
+
+        // openings at z
+        G1Point memory aggregated = proof.quotientParts[0];
+        uint256 zPower = 1;
+        for (uint256 i = 1; i < 4; i++) {
+            zPower = mulmod(zPower, state.zInDomainSize, R_MOD);
+            aggregated = pointAdd(aggregated, pointMul(proof.quotientParts[i], zPower));
+        }
+        uint256 aggregatedValue = proof.quotientAtZ;
+
+        uint256 challenge = state.v;
+        aggregated = pointAdd(aggregated, pointMul(linearizationCommitment(vk, proof, state), challenge));
+        aggregatedValue = addmod(aggregatedValue, mulmod(proof.linearizationAtZ, challenge, R_MOD), R_MOD);
+
+        for (uint256 i = 0; i < 4; i++) {
+            challenge = mulmod(challenge, state.v, R_MOD);
+            aggregated = pointAdd(aggregated, pointMul(proof.state[i], challenge));
+            aggregatedValue = addmod(aggregatedValue, mulmod(proof.stateAtZ[i], challenge, R_MOD), R_MOD);
+        }
+        for (uint256 i = 0; i < 3; i++) {
+            challenge = mulmod(challenge, state.v, R_MOD);
+            aggregated = pointAdd(aggregated, pointMul(vk.permutation[i], challenge));
+            aggregatedValue = addmod(aggregatedValue, mulmod(proof.sigmaAtZ[i], challenge, R_MOD), R_MOD);
+        }
+
+        challenge = mulmod(challenge, state.v, R_MOD);
+        aggregated = pointAdd(aggregated, pointMul(lookupTable, challenge));
+        aggregatedValue = addmod(aggregatedValue, mulmod(proof.lookupTAtZ, challenge, R_MOD), R_MOD);
+        challenge = mulmod(challenge, state.v, R_MOD);
+        aggregated = pointAdd(aggregated, pointMul(vk.lookupSelector, challenge));
+        aggregatedValue = addmod(aggregatedValue, mulmod(proof.lookupSelectorAtZ, challenge, R_MOD), R_MOD);
+        challenge = mulmod(challenge, state.v, R_MOD);
+        aggregated = pointAdd(aggregated, pointMul(vk.lookupTableType, challenge));
+        aggregatedValue = addmod(aggregatedValue, mulmod(proof.lookupTableTypeAtZ, challenge, R_MOD), R_MOD);
+
+        // openings at z * omega
+        G1Point[5] memory atZOmega = [
+            proof.copyPermutationGrandProduct,
+            proof.state[3],
+            proof.lookupS,
+            proof.lookupGrandProduct,
+            lookupTable
+        ];
+        uint256[5] memory valuesAtZOmega = [
+            proof.copyPermutationGrandProductAtZOmega,
+            proof.dAtZOmega,
+            proof.lookupSAtZOmega,
+            proof.lookupGrandProductAtZOmega,
+            proof.lookupTAtZOmega
+        ];
+        for (uint256 i = 0; i < 5; i++) {
+            challenge = mulmod(challenge, state.v, R_MOD);
+            uint256 scaled = mulmod(challenge, state.u, R_MOD);
+            aggregated = pointAdd(aggregated, pointMul(atZOmega[i], scaled));
+            aggregatedValue = addmod(aggregatedValue, mulmod(valuesAtZOmega[i], scaled, R_MOD), R_MOD);
+        }
+
+        aggregated = pointSub(aggregated, pointMul(G1Point(1, 2), aggregatedValue));
+
+        uint256 zOmega = mulmod(state.z, OMEGA, R_MOD);
+        G1Point memory pairWithGenerator = pointAdd(aggregated, pointMul(proof.openingAtZ, state.z));
+        pairWithGenerator = pointAdd(
+            pairWithGenerator,
+            pointMul(proof.openingAtZOmega, mulmod(zOmega, state.u, R_MOD))
+        );
+
+        G1Point memory pairWithX = pointAdd(proof.openingAtZ, pointMul(proof.openingAtZOmega, state.u));
+        pairWithX = pointNegate(pairWithX);
+
+        return pairing(pairWithGenerator, vk.g2Generator, pairWithX, vk.g2X);
+    }
+
+    function lagrangeAtZ(State memory state, uint256 index) internal view returns (uint256) {
+        // L_i(z) = omega^i * (z^n - 1) / (n * (z - omega^i))
+        uint256 omegaPower = fpow(OMEGA, index);
+        uint256 numerator = mulmod(omegaPower, fsub(state.zInDomainSize, 1), R_MOD);
+        uint256 denominator = mulmod(DOMAIN_SIZE, fsub(state.z, omegaPower), R_MOD);
+        return mulmod(numerator, fpow(denominator, R_MOD - 2), R_MOD);
+    }
+
+    function updateTranscript(Transcript memory transcript, uint256 value) internal pure {
+        bytes32 oldState0 = transcript.state0;
+        transcript.state0 = keccak256(abi.encodePacked(uint32(0), oldState0, transcript.state1, value));
+        transcript.state1 = keccak256(abi.encodePacked(uint32(1), oldState0, transcript.state1, value));
+    }
+
+    function updateTranscriptG1(Transcript memory transcript, G1Point memory point) internal pure {
+        updateTranscript(transcript, point.x);
+        updateTranscript(transcript, point.y);
+    }
+
+    function getChallenge(Transcript memory transcript) internal pure returns (uint256) {
+        bytes32 query = keccak256(
+            abi.encodePacked(uint32(2), transcript.state0, transcript.state1, transcript.counter)
+        );
+        transcript.counter += 1;
+        return uint256(query) & FR_MASK;
+    }
+
+    function checkedFr(uint256 value) internal pure returns (uint256) {
+        require(value < R_MOD, "field element is not reduced");
+        return value;
+    }
+
+    function checkedG1(uint256 x, uint256 y) internal pure returns (G1Point memory) {
+        require(x < Q_MOD && y < Q_MOD, "point coordinate is not reduced");
+        if (x != 0 || y != 0) {
+            // y^2 == x^3 + 3
+            uint256 lhs = mulmod(y, y, Q_MOD);
+            uint256 rhs = addmod(mulmod(mulmod(x, x, Q_MOD), x, Q_MOD), 3, Q_MOD);
+            require(lhs == rhs, "point is not on the curve");
+        }
+        return G1Point(x, y);
+    }
+
+    function fsub(uint256 a, uint256 b) internal pure returns (uint256) {
+        return addmod(a, R_MOD - b, R_MOD);
+    }
+
+    function fpow(uint256 base, uint256 exponent) internal view returns (uint256) {
+        uint256[6] memory input = [uint256(32), 32, 32, base, exponent, R_MOD];
+        uint256[1] memory output;
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x05, input, 0xc0, output, 0x20)
+        }
+        require(success, "modexp failed");
+        return output[0];
+    }
+
+    function pointAdd(G1Point memory p1, G1Point memory p2) internal view returns (G1Point memory r) {
+        uint256[4] memory input = [p1.x, p1.y, p2.x, p2.y];
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x06, input, 0x80, r, 0x40)
+        }
+        require(success, "ecAdd failed");
+    }
+
+    function pointMul(G1Point memory p, uint256 scalar) internal view returns (G1Point memory r) {
+        uint256[3] memory input = [p.x, p.y, scalar];
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x07, input, 0x60, r, 0x40)
+        }
+        require(success, "ecMul failed");
+    }
+
+    function pointNegate(G1Point memory p) internal pure returns (G1Point memory) {
+        if (p.x == 0 && p.y == 0) {
+            return p;
+        }
+        return G1Point(p.x, Q_MOD - p.y);
+    }
+
+    function pointSub(G1Point memory p1, G1Point memory p2) internal view returns (G1Point memory) {
+        return pointAdd(p1, pointNegate(p2));
+    }
+
+    function pairing(G1Point memory p1, uint256[4] memory g2First, G1Point memory p2, uint256[4] memory g2Second)
+        internal
+        view
+        returns (bool)
+    {
+        uint256[12] memory input = [
+            p1.x,
+            p1.y,
+            g2First[0],
+            g2First[1],
+            g2First[2],
+            g2First[3],
+            p2.x,
+            p2.y,
+            g2Second[0],
+            g2Second[1],
+            g2Second[2],
+            g2Second[3]
+        ];
+        uint256[1] memory output;
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x08, input, 0x180, output, 0x20)
+        }
+        require(success, "pairing failed");
+        return output[0] == 1;
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use compression::StorageTransition;
+use franklin_crypto::{
+    bellman::{
+        plonk::better_better_cs::cs::ConstraintSystem, Engine, Field, PrimeField, PrimeFieldRepr,
+        SynthesisError,
+    },
+    plonk::circuit::{
+        allocated_num::Num,
+        boolean::{AllocatedBit, Boolean},
+        byte::Byte,
+        linear_combination::LinearCombination,
+    },
+};
+
+use num_bigint::BigUint;
+
+use crate::config::TRANSITION_SIZE;
+use crate::main_circuit::{
+    allocate_and_prove_bytes, circuit_poseidon_hash, pack_bytes, RangeTable,
+};
+use crate::utils::*;
+use crate::witness::WitnessError;
+
+// The slot index is the whole slot hash, a Bn256 scalar has 254 bits. A shorter index
+// would let a prover grind a key into the slot of another one.
+pub const STATE_TREE_DEPTH: usize = 254;
+
+// Sparse Merkle tree of the storage slots, the native reference of the state section
+// of the circuit(`CircuitConfig::state_update`).
+//
+// The slot of `(address, key)` is the leaf `slot_index`, the leaf is
+// `poseidon(address, key, value)`, all of them packed by 31 bytes, or zero for a zero value
+// (an empty slot). Nodes are `poseidon(left, right)`. Two keys of the same index are
+// a Poseidon collision, `update` fails for the second one.
+#[derive(Clone)]
+pub struct StateTree<E: Engine> {
+    // hashes of the empty subtrees by level, level 0 is a leaf
+    empty: Vec<E::Fr>,
+    // non-empty nodes by level and index in the level
+    nodes: HashMap<(usize, BigUint), E::Fr>,
+    // the non-empty slots by index
+    slots: HashMap<BigUint, StorageTransition>,
+}
+
+// Witness of one transition: the value before it and the siblings from the leaf to the root.
+// Field elements are 32 big-endian bytes, see `utils::fe_to_bytes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotWitness {
+    pub old_value: [u8; STORAGE_KEY_OR_VALUE_SIZE],
+    pub siblings: Vec<[u8; 32]>,
+}
+
+// Witness of the state section for a batch, see `StateTree::apply`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateWitness {
+    pub old_root: [u8; 32],
+    pub new_root: [u8; 32],
+    pub slots: Vec<SlotWitness>,
+}
+
+impl<E: Engine> Default for StateTree<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Engine> StateTree<E> {
+    pub fn new() -> Self {
+        let mut empty = vec![E::Fr::zero()];
+        for level in 0..STATE_TREE_DEPTH {
+            empty.push(node_hash::<E>(&empty[level], &empty[level]));
+        }
+
+        Self {
+            empty,
+            nodes: HashMap::new(),
+            slots: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> E::Fr {
+        self.node(STATE_TREE_DEPTH, &BigUint::default())
+    }
+
+    pub fn get(
+        &self,
+        address: &[u8; ADDRESS_SIZE],
+        key: &[u8; STORAGE_KEY_OR_VALUE_SIZE],
+    ) -> [u8; STORAGE_KEY_OR_VALUE_SIZE] {
+        match self.slots.get(&slot_index::<E>(address, key)) {
+            Some(slot) if slot.address == *address && slot.key == *key => slot.value,
+            _ => [0; STORAGE_KEY_OR_VALUE_SIZE],
+        }
+    }
+
+    // Apply the transitions in order and return the witness of the batch.
+    // On error the tree is left with the transitions before the failed one applied.
+    pub fn apply(
+        &mut self,
+        transitions: &[StorageTransition],
+    ) -> Result<StateWitness, WitnessError> {
+        let old_root = fe_to_bytes::<E>(&self.root());
+        let slots = transitions
+            .iter()
+            .map(|transition| self.update(transition))
+            .collect::<Result<_, _>>()?;
+
+        Ok(StateWitness {
+            old_root,
+            new_root: fe_to_bytes::<E>(&self.root()),
+            slots,
+        })
+    }
+
+    pub fn update(&mut self, transition: &StorageTransition) -> Result<SlotWitness, WitnessError> {
+        let index = slot_index::<E>(&transition.address, &transition.key);
+        let old_value = match self.slots.get(&index) {
+            Some(slot) if slot.address != transition.address || slot.key != transition.key => {
+                return Err(WitnessError::SlotCollision { index });
+            }
+            Some(slot) => slot.value,
+            None => [0; STORAGE_KEY_OR_VALUE_SIZE],
+        };
+        let siblings: Vec<E::Fr> = (0..STATE_TREE_DEPTH)
+            .map(|level| self.node(level, &((&index >> level) ^ BigUint::from(1u32))))
+            .collect();
+
+        if transition.value == [0; STORAGE_KEY_OR_VALUE_SIZE] {
+            self.slots.remove(&index);
+        } else {
+            self.slots.insert(index.clone(), transition.clone());
+        }
+
+        let mut hash = leaf_hash::<E>(&transition.address, &transition.key, &transition.value);
+        for (level, sibling) in siblings.iter().enumerate() {
+            self.set_node(level, &index >> level, hash);
+            hash = if index.bit(level as u64) {
+                node_hash::<E>(sibling, &hash)
+            } else {
+                node_hash::<E>(&hash, sibling)
+            };
+        }
+        self.set_node(STATE_TREE_DEPTH, BigUint::default(), hash);
+
+        Ok(SlotWitness {
+            old_value,
+            siblings: siblings.iter().map(fe_to_bytes::<E>).collect(),
+        })
+    }
+
+    fn node(&self, level: usize, index: &BigUint) -> E::Fr {
+        self.nodes
+            .get(&(level, index.clone()))
+            .copied()
+            .unwrap_or(self.empty[level])
+    }
+
+    // Only the nodes with something below them are stored.
+    fn set_node(&mut self, level: usize, index: BigUint, hash: E::Fr) {
+        if hash == self.empty[level] {
+            self.nodes.remove(&(level, index));
+        } else {
+            self.nodes.insert((level, index), hash);
+        }
+    }
+}
+
+fn slot_input<E: Engine>(address: &[u8], key: &[u8]) -> Vec<E::Fr> {
+    let mut input = pack_bytes_to_field_elements::<E>(address);
+    input.extend(pack_bytes_to_field_elements::<E>(key));
+    input
+}
+
+pub fn slot_index<E: Engine>(
+    address: &[u8; ADDRESS_SIZE],
+    key: &[u8; STORAGE_KEY_OR_VALUE_SIZE],
+) -> BigUint {
+    let hash = poseidon_hash::<E>(&slot_input::<E>(address, key));
+    BigUint::from_bytes_le(&fe_to_le_bytes::<E>(&hash))
+}
+
+pub fn leaf_hash<E: Engine>(
+    address: &[u8; ADDRESS_SIZE],
+    key: &[u8; STORAGE_KEY_OR_VALUE_SIZE],
+    value: &[u8; STORAGE_KEY_OR_VALUE_SIZE],
+) -> E::Fr {
+    if *value == [0; STORAGE_KEY_OR_VALUE_SIZE] {
+        return E::Fr::zero();
+    }
+    let mut input = slot_input::<E>(address, key);
+    input.extend(pack_bytes_to_field_elements::<E>(value));
+    poseidon_hash::<E>(&input)
+}
+
+fn node_hash<E: Engine>(left: &E::Fr, right: &E::Fr) -> E::Fr {
+    poseidon_hash::<E>(&[*left, *right])
+}
+
+// Prove that the transitions in `data`(range checked bytes) applied one by one move the tree
+// from the old root to the new root, returns both roots. Circuit version of `StateTree::apply`.
+// circuit arithmetic
+pub(crate) fn enforce_state_update<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    data: &[Byte<E>],
+    state: Option<&StateWitness>,
+    range_table: &RangeTable,
+) -> Result<(Num<E>, Num<E>), SynthesisError> {
+    let old_root = Num::alloc(cs, state.map(|state| fe_from_bytes::<E>(&state.old_root)))?;
+
+    let mut root = old_root;
+    for (index, transition) in data.chunks(TRANSITION_SIZE).enumerate() {
+        let slot = state.and_then(|state| state.slots.get(index));
+        let (address, rest) = transition.split_at(ADDRESS_SIZE);
+        let (key, value) = rest.split_at(STORAGE_KEY_OR_VALUE_SIZE);
+        let old_value: Vec<Option<u8>> = match slot {
+            Some(slot) => slot.old_value.iter().map(|byte| Some(*byte)).collect(),
+            None => vec![None; STORAGE_KEY_OR_VALUE_SIZE],
+        };
+        let old_value = allocate_and_prove_bytes(
+            &old_value,
+            STORAGE_KEY_OR_VALUE_SIZE,
+            cs,
+            range_table,
+            false,
+        )?;
+
+        let mut slot_input = pack_bytes(cs, address)?;
+        slot_input.extend(pack_bytes(cs, key)?);
+        let slot_hash = circuit_poseidon_hash(cs, &slot_input)?;
+        let path = slot_path(cs, &slot_hash, range_table)?;
+
+        let mut old_hash = circuit_leaf_hash(cs, &slot_input, &old_value)?;
+        let mut new_hash = circuit_leaf_hash(cs, &slot_input, value)?;
+        for (level, is_right) in path.iter().enumerate() {
+            let sibling = Num::alloc(
+                cs,
+                slot.map(|slot| fe_from_bytes::<E>(&slot.siblings[level])),
+            )?;
+            old_hash = circuit_node_hash(cs, is_right, &old_hash, &sibling)?;
+            new_hash = circuit_node_hash(cs, is_right, &new_hash, &sibling)?;
+        }
+        old_hash.enforce_equal(cs, &root)?;
+        root = new_hash;
+    }
+
+    Ok((old_root, root))
+}
+
+// Bits of `slot_index`, the path from the leaf to the root.
+//
+// The hash is decomposed into bits as low + 2^64 * high and the decomposition must be
+// the canonical one(below the modulus): the bits of hash + modulus would be a second slot
+// for the same key.
+// circuit arithmetic
+fn slot_path<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    hash: &Num<E>,
+    range_table: &RangeTable,
+) -> Result<Vec<Boolean>, SynthesisError> {
+    const LOW_BITS: usize = 64;
+    let hash_bytes = hash.get_value().map(|hash| fe_to_le_bytes::<E>(&hash));
+    let modulus = E::Fr::char();
+    let modulus_low = modulus.as_ref()[0];
+    let mut modulus_high = modulus;
+    modulus_high.shr(LOW_BITS as u32);
+    let modulus_high = E::Fr::from_repr(modulus_high).unwrap();
+
+    let mut low = LinearCombination::zero();
+    let mut high = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    let mut bits = Vec::with_capacity(STATE_TREE_DEPTH);
+    for bit in 0..STATE_TREE_DEPTH {
+        if bit == LOW_BITS {
+            coeff = E::Fr::one();
+        }
+        let value = hash_bytes
+            .as_ref()
+            .map(|bytes| (bytes[bit / 8] >> (bit % 8)) & 1 == 1);
+        let bit_value = Boolean::Is(AllocatedBit::alloc(cs, value)?);
+        if bit < LOW_BITS {
+            low.add_assign_boolean_with_coeff(&bit_value, coeff);
+        } else {
+            high.add_assign_boolean_with_coeff(&bit_value, coeff);
+        }
+        coeff.double();
+        bits.push(bit_value);
+    }
+    let low = low.into_num(cs)?;
+    let high = high.into_num(cs)?;
+
+    // hash == low + 2^64 * high
+    let mut shift = E::Fr::one();
+    for _ in 0..LOW_BITS {
+        shift.double();
+    }
+    let mut decomposition = LinearCombination::zero();
+    decomposition.add_assign_number_with_coeff(&low, E::Fr::one());
+    decomposition.add_assign_number_with_coeff(&high, shift);
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    decomposition.add_assign_number_with_coeff(hash, minus_one);
+    decomposition
+        .into_num(cs)?
+        .enforce_equal(cs, &Num::zero())?;
+
+    // high <= modulus_high, the gap of a larger high wraps around the modulus
+    let high_len = (STATE_TREE_DEPTH - LOW_BITS).div_ceil(8);
+    let modulus_high = Num::Constant(modulus_high);
+    let gap = modulus_high.sub(cs, &high)?;
+    enforce_fits_bytes(cs, &gap, high_len, range_table)?;
+
+    // high == modulus_high => low < modulus_low
+    let is_max = Num::equals(cs, &high, &modulus_high)?;
+    let mut is_max_num = LinearCombination::zero();
+    is_max_num.add_assign_boolean_with_coeff(&is_max, E::Fr::one());
+    let is_max_num = is_max_num.into_num(cs)?;
+    let max_low = Num::Constant(E::Fr::from_str(&format!("{}", modulus_low - 1)).unwrap());
+    let low_gap = max_low.sub(cs, &low)?.mul(cs, &is_max_num)?;
+    enforce_fits_bytes(cs, &low_gap, LOW_BITS / 8, range_table)?;
+
+    Ok(bits)
+}
+
+// The number is below 2^(8 * len).
+// circuit arithmetic
+pub(crate) fn enforce_fits_bytes<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    num: &Num<E>,
+    len: usize,
+    range_table: &RangeTable,
+) -> Result<(), SynthesisError> {
+    let bytes: Vec<Option<u8>> = (0..len)
+        .map(|index| {
+            num.get_value()
+                .map(|value| fe_to_le_bytes::<E>(&value)[index])
+        })
+        .collect();
+    let bytes = allocate_and_prove_bytes(&bytes, len, cs, range_table, false)?;
+    le_bytes_to_num(cs, &bytes)?.enforce_equal(cs, num)
+}
+
+// circuit arithmetic
+pub(crate) fn le_bytes_to_num<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    bytes: &[Byte<E>],
+) -> Result<Num<E>, SynthesisError> {
+    let shift = E::Fr::from_str("256").unwrap();
+    let mut result = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for byte in bytes {
+        result.add_assign_number_with_coeff(&byte.inner, coeff);
+        coeff.mul_assign(&shift);
+    }
+    result.into_num(cs)
+}
+
+// Circuit version of `leaf_hash`.
+// circuit arithmetic
+fn circuit_leaf_hash<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    slot_input: &[Num<E>],
+    value: &[Byte<E>],
+) -> Result<Num<E>, SynthesisError> {
+    let value = pack_bytes(cs, value)?;
+    let mut input = slot_input.to_vec();
+    input.extend(value.iter().copied());
+    let hash = circuit_poseidon_hash(cs, &input)?;
+
+    // a zero value is an empty slot
+    let mut is_empty = Boolean::constant(true);
+    for part in value.iter() {
+        let is_zero = Num::equals(cs, part, &Num::zero())?;
+        is_empty = Boolean::and(cs, &is_empty, &is_zero)?;
+    }
+    let mut is_set = LinearCombination::zero();
+    is_set.add_assign_boolean_with_coeff(&is_empty.not(), E::Fr::one());
+    let is_set = is_set.into_num(cs)?;
+
+    hash.mul(cs, &is_set)
+}
+
+// Hash of the parent of `hash`, which is the right child if `is_right` is set.
+// circuit arithmetic
+fn circuit_node_hash<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    is_right: &Boolean,
+    hash: &Num<E>,
+    sibling: &Num<E>,
+) -> Result<Num<E>, SynthesisError> {
+    let mut swap = LinearCombination::zero();
+    swap.add_assign_boolean_with_coeff(is_right, E::Fr::one());
+    let swap = swap.into_num(cs)?;
+
+    let delta = sibling.sub(cs, hash)?.mul(cs, &swap)?;
+    let left = hash.add(cs, &delta)?;
+    let right = hash.add(cs, sibling)?.sub(cs, &left)?;
+
+    circuit_poseidon_hash(cs, &[left, right])
+}
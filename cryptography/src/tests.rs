@@ -0,0 +1,1555 @@
+use super::*;
+use crate::main_circuit::{allocate_and_prove_bytes, RangeTable};
+use crate::memory::ByteMemory;
+use crate::utils::{
+    compressed_data_commitment, public_input_commitment, ADDRESS_SIZE, STORAGE_KEY_OR_VALUE_SIZE,
+};
+use compression::{
+    sha3,
+    sha3::Digest,
+    utils::{compress_leading_zeroes, slot_from_preimage_and_offset},
+};
+use franklin_crypto::bellman::{
+    compact_bn256::{Fq, Fr, G1Affine, G2Affine},
+    plonk::{
+        better_better_cs::cs::{ConstraintSystem, Index, LookupTableApplication, PolyIdentifier},
+        commitments::transcript::Prng,
+    },
+    CurveAffine, Field, PrimeField, PrimeFieldRepr,
+};
+use franklin_crypto::plonk::circuit::{
+    allocated_num::Num, boolean::Boolean, byte::Byte, linear_combination::LinearCombination,
+};
+use std::collections::BTreeSet;
+
+type TestAssembly =
+    TrivialAssembly<Bn256, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>;
+
+fn transitions() -> Vec<StorageTransition> {
+    vec![StorageTransition {
+        address: [
+            211, 35, 126, 46, 74, 67, 213, 90, 55, 0, 12, 54, 222, 56, 77, 0, 132, 12, 1, 5,
+        ],
+        key: [
+            31, 8, 37, 27, 7, 64, 244, 1, 48, 0, 6, 0, 74, 0, 0, 249, 0, 0, 17, 0, 0, 0, 234, 0,
+            122, 65, 33, 0, 4, 0, 4, 11,
+        ],
+        value: [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+            72, 131, 3,
+        ],
+    }]
+}
+
+fn circuit(data: &[u8], compressed_data: &[u8]) -> CompressionCircuit<Bn256> {
+    circuit_with_config(data, compressed_data, CircuitConfig::default())
+}
+
+fn circuit_with_config(
+    data: &[u8],
+    compressed_data: &[u8],
+    config: CircuitConfig,
+) -> CompressionCircuit<Bn256> {
+    let witness = CompressionWitness::from_bytes(config, data, compressed_data).unwrap();
+    CompressionCircuit::new(config, &witness)
+}
+
+fn synthesize(circuit: &CompressionCircuit<Bn256>) -> TestAssembly {
+    let mut assembly = TestAssembly::new();
+    circuit.synthesize(&mut assembly).expect("must work");
+    assembly
+}
+
+// Malicious prover harness: overwrite one witness at a time with a forged flag or tag value
+// and return the indices of the witnesses that make the assembly satisfied.
+// Only the gates using the witness are re-checked, a witness not used by all the failing gates
+// can't fix them.
+fn forgeable_witnesses(mut assembly: TestAssembly) -> Vec<usize> {
+    let checker = debug::GateChecker::new(&assembly);
+    let failing: BTreeSet<usize> = (0..assembly.num_aux_gates)
+        .filter(|gate| checker.check(&assembly, *gate).is_some())
+        .collect();
+
+    // The gates of every aux witness, the d column is also read by the previous gate
+    let mut gates = vec![BTreeSet::new(); assembly.aux_assingments.len()];
+    for column in 0..4 {
+        let variables =
+            &assembly.aux_storage.state_map[&PolyIdentifier::VariablesPolynomial(column)];
+        for (gate, variable) in variables.iter().enumerate() {
+            if let Index::Aux(index) = variable.get_unchecked() {
+                gates[index - 1].insert(gate);
+                if column == 3 && gate > 0 {
+                    gates[index - 1].insert(gate - 1);
+                }
+            }
+        }
+    }
+
+    let values: Vec<Fr> = [0, 1, 3]
+        .into_iter()
+        .chain(11..=42)
+        .map(|value| Fr::from_str(&format!("{}", value)).unwrap())
+        .collect();
+    let mut result = Vec::new();
+    for (index, gates) in gates.iter().enumerate() {
+        if !failing.is_subset(gates) {
+            continue;
+        }
+        let honest = assembly.aux_assingments[index];
+        for forged in values.iter().filter(|value| **value != honest) {
+            assembly.aux_assingments[index] = *forged;
+            if gates
+                .iter()
+                .all(|gate| checker.check(&assembly, *gate).is_none())
+            {
+                result.push(index);
+            }
+        }
+        assembly.aux_assingments[index] = honest;
+    }
+    result
+}
+
+#[test]
+fn test_honest_witness_is_satisfied() {
+    let transitions = transitions();
+    let data = StorageTransition::into_bytes(transitions.clone());
+    let compressed_data = StorageTransition::compress(transitions);
+
+    assert!(synthesize(&circuit(&data, &compressed_data)).is_satisfied());
+}
+
+#[test]
+fn test_wrong_data_is_not_satisfied() {
+    let transitions = transitions();
+    let mut data = StorageTransition::into_bytes(transitions.clone());
+    let compressed_data = StorageTransition::compress(transitions);
+    *data.last_mut().unwrap() ^= 1;
+
+    assert!(!synthesize(&circuit(&data, &compressed_data)).is_satisfied());
+}
+
+// Malicious prover: the honest data with the compressed data which doesn't decompress into it.
+fn assert_rejected(compressed_data: &[u8]) {
+    let data = StorageTransition::into_bytes(transitions());
+    assert!(!synthesize(&circuit(&data, compressed_data)).is_satisfied());
+}
+
+// The honest encoding of `transitions()`: the tagged address, tag 0 key
+// and tag 38(28 leading zeroes) value.
+fn honest_compressed_data() -> Vec<u8> {
+    let compressed_data = StorageTransition::compress(transitions());
+    assert_eq!(compressed_data[0], 1);
+    assert_eq!(compressed_data[21], 0);
+    assert_eq!(compressed_data[54], 38);
+    compressed_data
+}
+
+#[test]
+fn test_wrong_tag_is_rejected() {
+    let mut compressed_data = honest_compressed_data();
+    compressed_data[0] = 0;
+    assert_rejected(&compressed_data);
+
+    let mut compressed_data = honest_compressed_data();
+    compressed_data[21] = 1;
+    assert_rejected(&compressed_data);
+}
+
+#[test]
+fn test_shifted_address_byte_is_rejected() {
+    let mut compressed_data = honest_compressed_data();
+    compressed_data[1..21].rotate_left(1);
+    assert_rejected(&compressed_data);
+}
+
+#[test]
+fn test_wrong_zero_count_is_rejected() {
+    // one more leading zero than the value has
+    let mut compressed_data = honest_compressed_data();
+    compressed_data[54] = 39;
+    compressed_data.remove(55);
+    assert_rejected(&compressed_data);
+
+    // one less, the value is shifted to the left
+    let mut compressed_data = honest_compressed_data();
+    compressed_data[54] = 37;
+    compressed_data.push(0);
+    assert_rejected(&compressed_data);
+}
+
+#[test]
+fn test_swapped_key_and_value_are_rejected() {
+    let mut swapped = transitions();
+    std::mem::swap(&mut swapped[0].key, &mut swapped[0].value);
+    assert_rejected(&StorageTransition::compress(swapped));
+}
+
+#[test]
+fn test_trailing_garbage_is_rejected() {
+    let mut compressed_data = honest_compressed_data();
+    compressed_data.extend([0xde, 0xad]);
+    assert_rejected(&compressed_data);
+
+    let gate = debug::check(&circuit(
+        &StorageTransition::into_bytes(transitions()),
+        &compressed_data,
+    ))
+    .unwrap()
+    .expect("trailing bytes must fail");
+    assert_eq!(gate.region, "compressed data length");
+}
+
+#[test]
+fn test_first_unsatisfied_gate_is_labeled() {
+    let transitions = transitions();
+    let mut data = StorageTransition::into_bytes(transitions.clone());
+    let compressed_data = StorageTransition::compress(transitions);
+    assert_eq!(
+        debug::check(&circuit(&data, &compressed_data)).unwrap(),
+        None
+    );
+
+    *data.last_mut().unwrap() ^= 1;
+    let gate = debug::check(&circuit(&data, &compressed_data))
+        .unwrap()
+        .expect("wrong data must fail");
+    assert_eq!(gate.kind, debug::GateKind::MainGate);
+    assert_eq!(gate.region, "word 2 value bytes");
+}
+
+// The word check flags and the tag constants used to be free witnesses,
+// so swapping a single one of them was enough to accept data that doesn't decompress.
+#[test]
+fn test_wrong_data_can_not_be_forged_by_one_witness() {
+    let transitions = transitions();
+    let mut data = StorageTransition::into_bytes(transitions.clone());
+    let compressed_data = StorageTransition::compress(transitions);
+    *data.last_mut().unwrap() ^= 1;
+
+    let assembly = synthesize(&circuit(&data, &compressed_data));
+    assert!(!assembly.is_satisfied());
+    assert_eq!(forgeable_witnesses(assembly), Vec::<usize>::new());
+}
+
+#[test]
+fn test_commitment_is_the_only_public_input() {
+    let transitions = transitions();
+    let data = StorageTransition::into_bytes(transitions.clone());
+    let compressed_data = StorageTransition::compress(transitions);
+    let config = CircuitConfig {
+        public_input: PublicInputMode::Commitment,
+        ..Default::default()
+    };
+
+    let assembly = synthesize(&circuit_with_config(&data, &compressed_data, config));
+    assert!(assembly.is_satisfied());
+    assert_eq!(
+        assembly.input_assingments,
+        vec![public_input_commitment::<Bn256>(
+            &sha3::Keccak256::digest(&compressed_data),
+            &sha3::Keccak256::digest(&data),
+            compressed_data.len(),
+            &[],
+        )]
+    );
+}
+
+#[test]
+fn test_poseidon_commitment_is_proven() {
+    let transitions = transitions();
+    let data = StorageTransition::into_bytes(transitions.clone());
+    let compressed_data = StorageTransition::compress(transitions);
+    let config = CircuitConfig {
+        commitment: CommitmentScheme::Poseidon,
+        ..Default::default()
+    };
+
+    let mut circuit = circuit_with_config(&data, &compressed_data, config);
+    assert!(synthesize(&circuit).is_satisfied());
+
+    circuit.compressed_data_hash[31] = circuit.compressed_data_hash[31].map(|byte| byte ^ 1);
+    assert!(!synthesize(&circuit).is_satisfied());
+
+    // the same field element, but not the canonical encoding
+    let mut modulus = Vec::new();
+    Fr::char().write_be(&mut modulus).unwrap();
+    let hash = compressed_data_commitment::<Bn256>(config, &compressed_data);
+    let overflow =
+        num_bigint::BigUint::from_bytes_be(&hash) + num_bigint::BigUint::from_bytes_be(&modulus);
+    let mut circuit = circuit_with_config(&data, &compressed_data, config);
+    circuit.compressed_data_hash = overflow.to_bytes_be().into_iter().map(Some).collect();
+    assert_eq!(circuit.compressed_data_hash.len(), 32);
+    assert!(!synthesize(&circuit).is_satisfied());
+}
+
+#[test]
+fn test_witness_capacity_is_checked() {
+    let config = CircuitConfig {
+        capacity: 2,
+        ..Default::default()
+    };
+    let transitions = vec![transitions()[0].clone(); 3];
+
+    assert_eq!(
+        CompressionWitness::from_transitions(config, &transitions),
+        Err(WitnessError::TooManyTransitions {
+            transitions: 3,
+            capacity: 2,
+        })
+    );
+    assert_eq!(
+        CompressionWitness::from_transitions(config, &transitions[..1]),
+        Err(WitnessError::PartialBatch {
+            transitions: 1,
+            capacity: 2,
+        })
+    );
+}
+
+#[test]
+fn test_proof_and_vk_serialization_roundtrip() {
+    let transitions = transitions();
+    let config = CircuitConfig::default();
+    let setup = setup(config, &CrsSource::InsecureDev).unwrap();
+    let proof = prove(&setup, &transitions).unwrap();
+    let public_inputs = CompressionWitness::from_transitions(config, &transitions)
+        .unwrap()
+        .public_inputs::<Bn256>(config);
+
+    for format in [Format::Binary, Format::Json] {
+        let mut proof_bytes = vec![];
+        serialization::write_proof(&proof, &mut proof_bytes, format).unwrap();
+        let mut vk_bytes = vec![];
+        serialization::write_vk(&setup.vk, &mut vk_bytes, format).unwrap();
+
+        let read_proof = serialization::read_proof(proof_bytes.as_slice(), format).unwrap();
+        let read_vk = serialization::read_vk(vk_bytes.as_slice(), format).unwrap();
+        assert!(verify(&read_vk, &read_proof, &public_inputs).unwrap());
+
+        let mut rewritten = vec![];
+        serialization::write_proof(&read_proof, &mut rewritten, format).unwrap();
+        assert_eq!(rewritten, proof_bytes);
+    }
+}
+
+#[test]
+fn test_json_field_elements_are_hex() {
+    let setup = setup(CircuitConfig::default(), &CrsSource::InsecureDev).unwrap();
+    let proof = prove(&setup, &transitions()).unwrap();
+    let hex = |repr: &dyn std::fmt::Display| serde_json::Value::String(format!("{repr}"));
+
+    let mut bytes = vec![];
+    serialization::write_proof(&proof, &mut bytes, Format::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(json["n"], proof.n);
+    for (index, input) in proof.inputs.iter().enumerate() {
+        assert_eq!(json["inputs"][index], hex(&input.into_repr()));
+    }
+    let (x, y) = proof.opening_proof_at_z.into_xy_unchecked();
+    assert_eq!(json["opening_proof_at_z"]["x"], hex(&x.into_repr()));
+    assert_eq!(json["opening_proof_at_z"]["y"], hex(&y.into_repr()));
+    assert_eq!(
+        json["quotient_poly_opening_at_z"],
+        hex(&proof.quotient_poly_opening_at_z.into_repr())
+    );
+    assert_eq!(json["inputs"][0].as_str().unwrap().len(), 2 + 64);
+
+    let mut bytes = vec![];
+    serialization::write_vk(&setup.vk, &mut bytes, Format::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    for (index, non_residue) in setup.vk.non_residues.iter().enumerate() {
+        assert_eq!(json["non_residues"][index], hex(&non_residue.into_repr()));
+    }
+
+    // not a field element
+    let invalid = String::from_utf8(bytes)
+        .unwrap()
+        .replacen("\"0x", "\"0xz", 1);
+    assert!(serialization::read_vk(invalid.as_bytes(), Format::Json).is_err());
+}
+
+#[test]
+fn test_solidity_verifier_and_calldata() {
+    let transitions = transitions();
+    let setup = setup(CircuitConfig::default(), &CrsSource::InsecureDev).unwrap();
+    let proof = prove(&setup, &transitions).unwrap();
+
+    let verifier = solidity::generate_verifier(&setup.vk);
+    assert!(!verifier.contains("{{"));
+    assert!(verifier.contains(&format!("DOMAIN_SIZE = {};", setup.vk.n + 1)));
+
+    let calldata = solidity::calldata(&proof);
+    let words = 2 + 1 + proof.inputs.len() + 1 + solidity::SERIALIZED_PROOF_LENGTH;
+    assert_eq!(calldata.len(), 4 + 32 * words);
+    let proof_offset = 4 + 32 * (3 + proof.inputs.len());
+    assert_eq!(
+        calldata[proof_offset + 31] as usize,
+        solidity::SERIALIZED_PROOF_LENGTH
+    );
+    assert_eq!(
+        calldata[proof_offset + 32..],
+        solidity::serialize_proof(&proof).concat()[..]
+    );
+}
+
+thread_local! {
+    static RECORDED_CHALLENGES: std::cell::RefCell<Vec<[u8; 32]>> =
+        std::cell::RefCell::new(Vec::new());
+}
+
+// `RollingKeccakTranscript` which records every challenge it gives to the verifier.
+#[derive(Clone)]
+struct RecordingTranscript(RollingKeccakTranscript<Fr>);
+
+impl Prng<Fr> for RecordingTranscript {
+    type Input = Fr;
+    type InitializationParameters = ();
+
+    fn new() -> Self {
+        Self(RollingKeccakTranscript::new())
+    }
+
+    fn commit_input(&mut self, input: &Self::Input) {
+        self.0.commit_input(input);
+    }
+
+    fn get_challenge(&mut self) -> Fr {
+        let challenge = self.0.get_challenge();
+        RECORDED_CHALLENGES.with(|log| log.borrow_mut().push(solidity::fe_to_bytes(&challenge)));
+        challenge
+    }
+}
+
+impl Transcript<Fr> for RecordingTranscript {
+    fn commit_bytes(&mut self, bytes: &[u8]) {
+        self.0.commit_bytes(bytes);
+    }
+
+    fn commit_field_element(&mut self, element: &Fr) {
+        self.0.commit_field_element(element);
+    }
+
+    fn get_challenge_bytes(&mut self) -> Vec<u8> {
+        self.0.get_challenge_bytes()
+    }
+
+    fn commit_fe<FF: PrimeField>(&mut self, element: &FF) {
+        self.0.commit_fe(element);
+    }
+}
+
+// The transcript of the generated verifier, `updateTranscript` and `getChallenge`
+// written out in Rust.
+#[derive(Default)]
+struct SolidityTranscript {
+    state0: [u8; 32],
+    state1: [u8; 32],
+    counter: u32,
+}
+
+impl SolidityTranscript {
+    fn update(&mut self, value: &[u8; 32]) {
+        let hash = |tag: u32| -> [u8; 32] {
+            let mut hasher = sha3::Keccak256::new();
+            hasher.update(tag.to_be_bytes());
+            hasher.update(self.state0);
+            hasher.update(self.state1);
+            hasher.update(value);
+            hasher.finalize().into()
+        };
+        let (state0, state1) = (hash(0), hash(1));
+        self.state0 = state0;
+        self.state1 = state1;
+    }
+
+    fn challenge(&mut self, mask: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = sha3::Keccak256::new();
+        hasher.update(2u32.to_be_bytes());
+        hasher.update(self.state0);
+        hasher.update(self.state1);
+        hasher.update(self.counter.to_be_bytes());
+        self.counter += 1;
+        let mut challenge: [u8; 32] = hasher.finalize().into();
+        for (byte, mask) in challenge.iter_mut().zip(mask) {
+            *byte &= mask;
+        }
+        challenge
+    }
+}
+
+// Statements of the given function of the verifier with the loops unrolled,
+// `[i]` is replaced with the iteration.
+fn solidity_statements(verifier: &str, function: &str, inputs: usize) -> Vec<String> {
+    let start = verifier
+        .find(&format!("function {function}("))
+        .expect("function is in the verifier");
+    let open = start + verifier[start..].find('{').unwrap();
+    let mut depth = 0;
+    let mut close = open;
+    for (i, c) in verifier[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = open + i;
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut lines = verifier[open + 1..close]
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"));
+    let mut statements = Vec::new();
+    while let Some(line) = lines.next() {
+        let Some(bound) = line
+            .strip_prefix("for (uint256 i = 0; i < ")
+            .and_then(|rest| rest.strip_suffix("; i++) {"))
+        else {
+            statements.push(line.to_string());
+            continue;
+        };
+        let bound = match bound {
+            "publicInputs.length" | "proof.inputs.length" => inputs,
+            bound => bound.parse().expect("constant loop bound"),
+        };
+        let body: Vec<&str> = lines.by_ref().take_while(|line| *line != "}").collect();
+        for i in 0..bound {
+            for line in body.iter() {
+                statements.push(line.replace("[i]", &format!("[{i}]")));
+            }
+        }
+    }
+    statements
+}
+
+// Run `deserializeProof` and `computeChallenges` of the verifier on the calldata words,
+// returns the challenges in the order the contract computes them.
+fn solidity_challenges(verifier: &str, proof: &CompressionProof) -> Vec<[u8; 32]> {
+    let inputs: Vec<[u8; 32]> = proof.inputs.iter().map(solidity::fe_to_bytes).collect();
+    let serialized = solidity::serialize_proof(proof);
+
+    let mut fields = std::collections::HashMap::new();
+    let mut j = 0;
+    for statement in solidity_statements(verifier, "deserializeProof", inputs.len()) {
+        if statement.starts_with("require(") || statement.starts_with("proof.inputs = ") {
+            continue;
+        }
+        if statement == "uint256 j = 0;" {
+            j = 0;
+            continue;
+        }
+        if statement == "j += 2;" {
+            j += 2;
+            continue;
+        }
+        let (target, expr) = statement
+            .strip_prefix("proof.")
+            .and_then(|statement| statement.split_once(" = "))
+            .unwrap_or_else(|| panic!("unexpected statement {statement}"));
+        let words = match expr {
+            "checkedG1(serialized[j], serialized[j + 1]);" => serialized[j..j + 2].to_vec(),
+            "checkedFr(serialized[j++]);" => {
+                j += 1;
+                vec![serialized[j - 1]]
+            }
+            _ => {
+                let index = expr
+                    .strip_prefix("checkedFr(publicInputs[")
+                    .and_then(|rest| rest.strip_suffix("]);"))
+                    .unwrap_or_else(|| panic!("unexpected statement {statement}"));
+                vec![inputs[index.parse::<usize>().unwrap()]]
+            }
+        };
+        fields.insert(target.to_string(), words);
+    }
+    assert_eq!(j + 2, serialized.len(), "every word is deserialized");
+
+    let mask_hex = verifier
+        .split("uint256 constant FR_MASK = 0x")
+        .nth(1)
+        .and_then(|rest| rest.split(';').next())
+        .unwrap();
+    let mut mask = [0u8; 32];
+    for (byte, i) in mask.iter_mut().zip((0..64).step_by(2)) {
+        *byte = u8::from_str_radix(&mask_hex[i..i + 2], 16).unwrap();
+    }
+
+    let mut transcript = SolidityTranscript::default();
+    let mut challenges = Vec::new();
+    for statement in solidity_statements(verifier, "computeChallenges", inputs.len()) {
+        let update = statement
+            .strip_prefix("updateTranscript(transcript, proof.")
+            .map(|field| (field, 1))
+            .or_else(|| {
+                statement
+                    .strip_prefix("updateTranscriptG1(transcript, proof.")
+                    .map(|field| (field, 2))
+            });
+        if let Some((field, len)) = update {
+            let words = &fields[field.strip_suffix(");").unwrap()];
+            assert_eq!(words.len(), len, "{statement}");
+            for word in words {
+                transcript.update(word);
+            }
+        } else if statement.ends_with(" = getChallenge(transcript);") {
+            challenges.push(transcript.challenge(&mask));
+        }
+    }
+    challenges
+}
+
+fn recorded_challenges(setup: &CircuitSetup, proof: &CompressionProof) -> (bool, Vec<[u8; 32]>) {
+    RECORDED_CHALLENGES.with(|log| log.borrow_mut().clear());
+    let valid = verifier::verify::<Bn256, CompressionCircuit<Bn256>, RecordingTranscript>(
+        &setup.vk, proof, None,
+    )
+    .unwrap();
+    (valid, RECORDED_CHALLENGES.with(|log| log.take()))
+}
+
+#[test]
+fn test_solidity_transcript_matches_the_verifier() {
+    let setup = setup(CircuitConfig::default(), &CrsSource::InsecureDev).unwrap();
+    let proof = prove(&setup, &transitions()).unwrap();
+    let verifier = solidity::generate_verifier(&setup.vk);
+
+    let (valid, recorded) = recorded_challenges(&setup, &proof);
+    assert!(valid);
+    let challenges = solidity_challenges(&verifier, &proof);
+    // eta, beta, gamma, beta and gamma of the lookup, alpha, z, v and u
+    assert_eq!(challenges.len(), 9);
+    assert_eq!(challenges, recorded);
+
+    // The openings are absorbed before v, so v and u change and the proof is rejected.
+    let mut tampered = proof.clone();
+    tampered
+        .linearization_poly_opening_at_z
+        .add_assign(&Fr::one());
+    let (valid, recorded) = recorded_challenges(&setup, &tampered);
+    assert!(!valid);
+    let tampered_challenges = solidity_challenges(&verifier, &tampered);
+    assert!(!recorded.is_empty());
+    assert_eq!(tampered_challenges[..recorded.len()], recorded[..]);
+    assert_eq!(tampered_challenges[..7], challenges[..7]);
+    assert_ne!(tampered_challenges[7..], challenges[7..]);
+}
+
+#[test]
+fn test_crs_without_enough_points_is_rejected() {
+    let worker = franklin_crypto::bellman::worker::Worker::new();
+    let path = std::env::temp_dir().join("compression_test_crs_16.key");
+    let mut file = std::fs::File::create(&path).unwrap();
+    CompressionCrs::crs_42(16, &worker)
+        .write(&mut file)
+        .unwrap();
+
+    let crs = CrsSource::file(&path, CrsFormat::Bellman);
+    assert!(crs.load(16).is_ok());
+    assert!(matches!(
+        crs.load(32),
+        Err(Error::Crs(CrsError::NotEnoughPoints {
+            available: 16,
+            required: 32,
+        }))
+    ));
+}
+
+// The powers of `crs_42` written in the format of the ceremony and read back,
+// must be the same as the ones read from the Bellman encoding.
+fn assert_same_crs_as_bellman(name: &str, format: CrsFormat, bytes: &[u8]) {
+    let worker = franklin_crypto::bellman::worker::Worker::new();
+    let bellman_path = std::env::temp_dir().join(format!("compression_test_crs_{name}.key"));
+    let mut file = std::fs::File::create(&bellman_path).unwrap();
+    CompressionCrs::crs_42(16, &worker)
+        .write(&mut file)
+        .unwrap();
+    let path = std::env::temp_dir().join(format!("compression_test_crs_{name}.dat"));
+    std::fs::write(&path, bytes).unwrap();
+
+    let expected = CrsSource::file(&bellman_path, CrsFormat::Bellman)
+        .load(16)
+        .unwrap();
+    let crs = CrsSource::file(&path, format).load(16).unwrap();
+    assert_eq!(crs.g1_bases, expected.g1_bases);
+    assert_eq!(crs.g2_monomial_bases, expected.g2_monomial_bases);
+}
+
+// 4 big-endian limbs, the least significant one first, not in Montgomery form.
+fn ignition_fq(fq: &Fq) -> Vec<u8> {
+    fq.into_repr()
+        .as_ref()
+        .iter()
+        .flat_map(|limb| limb.to_be_bytes())
+        .collect()
+}
+
+#[test]
+fn test_ignition_crs() {
+    let worker = franklin_crypto::bellman::worker::Worker::new();
+    let crs = CompressionCrs::crs_42(16, &worker);
+
+    // transcript 0 of 1 with 15 G1 points from [x]_1 and [x]_2
+    let mut bytes: Vec<u8> = [0u32, 1, 15, 1, 15, 1, 0]
+        .iter()
+        .flat_map(|field| field.to_be_bytes())
+        .collect();
+    for point in crs.g1_bases[1..].iter() {
+        let (x, y) = point.into_xy_unchecked();
+        bytes.extend(ignition_fq(&x));
+        bytes.extend(ignition_fq(&y));
+    }
+    let (x, y) = crs.g2_monomial_bases[1].into_xy_unchecked();
+    for fq in [x.c0, x.c1, y.c0, y.c1] {
+        bytes.extend(ignition_fq(&fq));
+    }
+
+    assert_same_crs_as_bellman("ignition", CrsFormat::Ignition, &bytes);
+}
+
+// Published [x]_2 of the Ignition ceremony, the one hardcoded by the verifier contracts
+// built on it, imaginary part first as in the precompile input.
+const IGNITION_X_G2: [&str; 4] = [
+    "260e01b251f6f1c7e7ff4e580791dee8ea51d87a358e038b4efe30fac09383c1",
+    "0118c4d5b837bcc2bc89b5b398b5974e9f5944073b32078b7e231fec938883b0",
+    "04fc6369f7110fe3d25156c1bb9a72859cf2a04641f99ba4ee413c80da6a5fe4",
+    "22febda3c0c0632a56475b4214e5615e11e6dd3f96e6cea2854a87d4dacc5e55",
+];
+
+#[test]
+fn test_ignition_crs_reads_the_published_x_g2() {
+    // The layout of transcript00.dat written out by hand: the manifest(transcript 0 of 1,
+    // 1 G1 and 1 G2 point from [x]_1), one G1 point and [x]_2, every coordinate as
+    // 4 big-endian u64 limbs with the least significant first, c0 before c1.
+    // The generator stands in for [x]_1, only the decoding is checked here.
+    let fixture = [
+        "00000000000000010000000100000001000000010000000100000000",
+        "0000000000000001000000000000000000000000000000000000000000000000",
+        "0000000000000002000000000000000000000000000000000000000000000000",
+        "7e231fec938883b09f5944073b32078bbc89b5b398b5974e0118c4d5b837bcc2",
+        "4efe30fac09383c1ea51d87a358e038be7ff4e580791dee8260e01b251f6f1c7",
+        "854a87d4dacc5e5511e6dd3f96e6cea256475b4214e5615e22febda3c0c0632a",
+        "ee413c80da6a5fe49cf2a04641f99ba4d25156c1bb9a728504fc6369f7110fe3",
+    ]
+    .concat();
+    let hex_bytes = |hex: &str| -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    };
+    let path = std::env::temp_dir().join("compression_test_crs_ignition_x_g2.dat");
+    std::fs::write(&path, hex_bytes(&fixture)).unwrap();
+
+    let crs = CrsSource::file(&path, CrsFormat::Ignition).load(2).unwrap();
+    assert_eq!(crs.g1_bases, vec![G1Affine::one(); 2]);
+    assert_eq!(crs.g2_monomial_bases[0], G2Affine::one());
+    let (x, y) = crs.g2_monomial_bases[1].into_xy_unchecked();
+    for (fq, expected) in [x.c1, x.c0, y.c1, y.c0].iter().zip(IGNITION_X_G2) {
+        assert_eq!(solidity::fe_to_bytes(fq).to_vec(), hex_bytes(expected));
+    }
+
+    // with the first and the last limb of x.c0 swapped it is bigger than the modulus
+    let mut swapped = hex_bytes(&fixture);
+    swapped[28 + 128..28 + 136].copy_from_slice(&hex_bytes("0118c4d5b837bcc2"));
+    swapped[28 + 152..28 + 160].copy_from_slice(&hex_bytes("7e231fec938883b0"));
+    std::fs::write(&path, swapped).unwrap();
+    assert!(matches!(
+        CrsSource::file(&path, CrsFormat::Ignition).load(2),
+        Err(Error::Crs(CrsError::InvalidFile(_)))
+    ));
+}
+
+#[test]
+fn test_powers_of_tau_crs() {
+    let worker = franklin_crypto::bellman::worker::Worker::new();
+    // 2^4 powers: 31 G1 and 16 G2 powers of tau, only the first 16 and 2 are read
+    let powers = 16;
+    let crs = CompressionCrs::crs_42(2 * powers, &worker);
+
+    let mut bytes = vec![0u8; 64];
+    for point in crs.g1_bases[..2 * powers - 1].iter() {
+        bytes.extend(point.into_uncompressed().as_ref());
+    }
+    let mut g2 = crs.g2_monomial_bases.clone();
+    g2.resize(powers, G2Affine::one());
+    for point in g2.iter() {
+        bytes.extend(point.into_uncompressed().as_ref());
+    }
+    // alpha and beta powers in G1, beta in G2
+    for _ in 0..2 * powers {
+        bytes.extend(G1Affine::one().into_uncompressed().as_ref());
+    }
+    bytes.extend(G2Affine::one().into_uncompressed().as_ref());
+
+    assert_same_crs_as_bellman("powers_of_tau", CrsFormat::PowersOfTau, &bytes);
+}
+
+#[test]
+fn test_setup_cache_is_keyed_by_circuit() {
+    let dir = std::env::temp_dir().join("compression_test_setup_cache");
+    let _ = std::fs::remove_dir_all(&dir);
+    let cache = SetupCache::new(&dir);
+    let crs = CrsSource::InsecureDev;
+
+    let vk_bytes = |setup: &CircuitSetup| {
+        let mut bytes = vec![];
+        serialization::write_vk(&setup.vk, &mut bytes, Format::Binary).unwrap();
+        bytes
+    };
+
+    let created = cache.setup(CircuitConfig::default(), &crs).unwrap();
+    let cached = cache.setup(CircuitConfig::default(), &crs).unwrap();
+    assert_eq!(vk_bytes(&created), vk_bytes(&cached));
+    assert_eq!(created.shape, cached.shape);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    let config = CircuitConfig {
+        public_input: PublicInputMode::Commitment,
+        ..Default::default()
+    };
+    let other = cache.setup(config, &crs).unwrap();
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 4);
+    assert_ne!(other.shape, created.shape);
+}
+
+// A changed circuit or CRS file must not hit the entry of the old one.
+#[test]
+fn test_setup_cache_key_follows_circuit_and_crs() {
+    let config = CircuitConfig::default();
+    let shape = shape_of(config);
+    let crs = CrsSource::InsecureDev.load(1 << 10).unwrap();
+    let key = crate::cache::cache_key(config, shape, &crs).unwrap();
+    assert_eq!(key, crate::cache::cache_key(config, shape, &crs).unwrap());
+
+    let mut other_shape = shape;
+    other_shape[0] ^= 1;
+    assert_ne!(
+        key,
+        crate::cache::cache_key(config, other_shape, &crs).unwrap()
+    );
+
+    let other_crs = CompressionCrs::dummy_crs(1 << 10);
+    assert_ne!(
+        key,
+        crate::cache::cache_key(config, shape, &other_crs).unwrap()
+    );
+}
+
+#[test]
+fn test_setup_of_another_circuit_is_rejected() {
+    let transitions = transitions();
+    let mut setup = setup(CircuitConfig::default(), &CrsSource::InsecureDev).unwrap();
+    assert!(prove(&setup, &transitions).is_ok());
+
+    let pair_range_table = CircuitConfig {
+        pair_range_table: true,
+        ..Default::default()
+    };
+    setup.shape = shape_of(pair_range_table);
+    assert!(matches!(
+        prove(&setup, &transitions),
+        Err(Error::SetupMismatch)
+    ));
+}
+
+// `CircuitSetup::shape` of the config, without the setup itself.
+fn shape_of(config: CircuitConfig) -> [u8; 32] {
+    crate::cache::shape_hash(&crate::synthesize_setup_assembly(config).unwrap())
+}
+
+#[test]
+fn test_profile_covers_every_gate() {
+    let config = CircuitConfig::default();
+    let profile = profile::profile(config).unwrap();
+
+    let mut assembly = crate::CompressionSetupAssembly::new();
+    CompressionCircuit::<Bn256>::empty(config)
+        .synthesize(&mut assembly)
+        .unwrap();
+    assert_eq!(profile.total.gates, assembly.num_aux_gates);
+
+    let values = profile
+        .regions
+        .iter()
+        .find(|cost| cost.region == "word N value bytes")
+        .unwrap();
+    assert_eq!(values.instances, 2 * config.capacity);
+    // one dynamic read per byte, not a comparison with every encoding
+    assert!(values.gates / values.instances < 32 * 16);
+    assert_eq!(
+        profile.regions[0].lookups, 32,
+        "one lookup per compressed data hash byte"
+    );
+}
+
+// A memory read costs the same for any number of cells, so every added transition
+// adds the same number of read gates.
+#[test]
+fn test_memory_read_cost_is_constant() {
+    let read_gates = |capacity| {
+        let profile = profile::profile(CircuitConfig {
+            capacity,
+            ..Default::default()
+        })
+        .unwrap();
+        let read = profile
+            .regions
+            .iter()
+            .find(|cost| cost.region == "word N read")
+            .unwrap();
+        read.gates
+    };
+
+    assert_eq!(read_gates(4) - read_gates(3), read_gates(3) - read_gates(2));
+}
+
+// Assembly with the byte range table and the range checked bytes.
+fn assembly_with_bytes(bytes: &[u8]) -> (TestAssembly, RangeTable, Vec<Byte<Bn256>>) {
+    let mut cs = TestAssembly::new();
+    let columns = (0..3).map(PolyIdentifier::VariablesPolynomial).collect();
+    let table = LookupTableApplication::new_range_table_of_width_3(8, columns).unwrap();
+    let range_table = RangeTable {
+        name: table.functional_name(),
+        bytes_per_row: 1,
+    };
+    cs.add_table(table).unwrap();
+    let values = bytes.iter().map(|byte| Some(*byte)).collect();
+    let bytes =
+        allocate_and_prove_bytes(&values, bytes.len(), &mut cs, &range_table, false).unwrap();
+    (cs, range_table, bytes)
+}
+
+fn constant(value: usize) -> Num<Bn256> {
+    Num::Constant(Fr::from_str(&value.to_string()).unwrap())
+}
+
+// The read of the baseline `get_word_from_bytes`: the address is compared with every cell.
+fn baseline_read(cs: &mut TestAssembly, bytes: &[Byte<Bn256>], address: &Num<Bn256>) -> Num<Bn256> {
+    let mut result = Num::zero();
+    for (index, byte) in bytes.iter().enumerate() {
+        let flag = Num::equals(cs, &constant(index), address).unwrap();
+        let term = Num::from_boolean_is(flag).mul(cs, &byte.inner).unwrap();
+        result = result.add(cs, &term).unwrap();
+    }
+    result
+}
+
+// The reads of the default capacity(33 bytes per word) from its max-size compressed data,
+// with the baseline reads and with `ByteMemory`, including its consistency argument.
+// `cargo test test_memory_read_cost_against_baseline -- --nocapture` prints the counts.
+#[test]
+fn test_memory_read_cost_against_baseline() {
+    let config = CircuitConfig::default();
+    let len = config.max_compressed_data_size();
+    let bytes: Vec<u8> = (0..len).map(|index| index as u8).collect();
+    let reads = 33 * config.words();
+
+    let read_gates = |byte_memory: bool| {
+        let (mut cs, range_table, cells) = assembly_with_bytes(&bytes);
+        let ptr = Num::alloc(&mut cs, Some(Fr::zero())).unwrap();
+        let start = cs.n();
+        let mut memory = ByteMemory::new(cells.clone());
+        for index in 0..reads {
+            let address = ptr.add(&mut cs, &constant(index % len)).unwrap();
+            let value = if byte_memory {
+                memory.read(&mut cs, &address).unwrap().inner
+            } else {
+                baseline_read(&mut cs, &cells, &address)
+            };
+            value
+                .enforce_equal(&mut cs, &constant(bytes[index % len] as usize))
+                .unwrap();
+        }
+        if byte_memory {
+            memory
+                .enforce_reads_consistency(&mut cs, &range_table)
+                .unwrap();
+        }
+        assert!(cs.is_satisfied());
+        cs.n() - start
+    };
+
+    let (baseline, byte_memory) = (read_gates(false), read_gates(true));
+    println!(
+        "{reads} reads of {len} bytes: {baseline} gates before, {byte_memory} with ByteMemory"
+    );
+    assert!(byte_memory < baseline);
+}
+
+// The value word check before the shifted reads: the 33 bytes at `ptr` are compared with
+// the raw encoding and with every leading-zero encoding.
+fn legacy_value_word(
+    cs: &mut TestAssembly,
+    memory: &mut ByteMemory<Bn256>,
+    ptr: &Num<Bn256>,
+    data: &[Byte<Bn256>],
+) {
+    let mut word = Vec::with_capacity(33);
+    for index in 0..33 {
+        let address = ptr.add(cs, &constant(index)).unwrap();
+        word.push(memory.read(cs, &address).unwrap().inner);
+    }
+
+    let zero = Num::zero();
+    let mut ok = Boolean::constant(false);
+    let mut word_len = LinearCombination::zero();
+
+    let tag_is0 = Num::equals(cs, &word[0], &zero).unwrap();
+    let mut is0 = tag_is0;
+    for (index, byte) in data.iter().enumerate() {
+        let eq = Num::equals(cs, &byte.inner, &word[index + 1]).unwrap();
+        is0 = Boolean::and(cs, &is0, &eq).unwrap();
+    }
+    ok = Boolean::or(cs, &ok, &is0).unwrap();
+    word_len.add_assign_boolean_with_coeff(&tag_is0, Fr::from_str("33").unwrap());
+
+    for tag in 11..=42 {
+        let zeroes = tag - 10;
+        let tag_is = Num::equals(cs, &word[0], &constant(tag)).unwrap();
+        let mut is = tag_is;
+        for (index, byte) in data.iter().enumerate() {
+            let expected = if index < zeroes {
+                zero
+            } else {
+                word[index - zeroes + 1]
+            };
+            let eq = Num::equals(cs, &byte.inner, &expected).unwrap();
+            is = Boolean::and(cs, &is, &eq).unwrap();
+        }
+        ok = Boolean::or(cs, &ok, &is).unwrap();
+        word_len
+            .add_assign_boolean_with_coeff(&tag_is, Fr::from_str(&(43 - tag).to_string()).unwrap());
+    }
+
+    let word_len = word_len.into_num(cs).unwrap();
+    ptr.add(cs, &word_len).unwrap();
+    Boolean::enforce_equal(cs, &ok, &Boolean::constant(true)).unwrap();
+}
+
+// Gates of the value words before and after the shifted reads, for 3 words(capacity 1) and
+// 66 words(capacity 22, 64 words is not a whole number of transitions). Both read 33 bytes
+// per value word, so the consistency argument of the reads is the same and is left out.
+// The circuit before is the one of today with the legacy value words.
+// `cargo test test_value_word_cost_against_the_branches -- --nocapture` prints the counts.
+#[test]
+fn test_value_word_cost_against_the_branches() {
+    let mut compressed_data = vec![0u8];
+    compressed_data.extend(1..=32u8);
+    let (mut cs, range_table, cells) = assembly_with_bytes(&compressed_data);
+    let value: Vec<Option<u8>> = (1..=32u8).map(Some).collect();
+    let data = allocate_and_prove_bytes(&value, 32, &mut cs, &range_table, false).unwrap();
+    let ptr = Num::alloc(&mut cs, Some(Fr::zero())).unwrap();
+    let mut memory = ByteMemory::new(cells);
+    let start = cs.n();
+    legacy_value_word(&mut cs, &mut memory, &ptr, &data);
+    let legacy = cs.n() - start;
+    assert!(cs.is_satisfied());
+
+    let value_regions = [
+        "word N tag",
+        "word N leading zeroes",
+        "word N value bytes",
+        "word N length",
+    ];
+    for capacity in [1, 22] {
+        let profile = profile::profile(CircuitConfig {
+            capacity,
+            ..Default::default()
+        })
+        .unwrap();
+        let value_words = 2 * capacity;
+        let shifted: usize = profile
+            .regions
+            .iter()
+            .filter(|cost| value_regions.contains(&cost.region.as_str()))
+            .map(|cost| cost.gates)
+            .sum();
+        let after = profile.total.gates;
+        let before = after - shifted + value_words * legacy;
+        println!(
+            "{} words: {before} gates before, {after} after ({legacy} and {} per value word)",
+            3 * capacity,
+            shifted / value_words
+        );
+        assert!(shifted < value_words * legacy);
+    }
+}
+
+#[test]
+fn test_pair_range_table() {
+    let config = CircuitConfig {
+        pair_range_table: true,
+        ..Default::default()
+    };
+    let transitions = transitions();
+    let data = StorageTransition::into_bytes(transitions.clone());
+    let compressed_data = StorageTransition::compress(transitions);
+    assert!(synthesize(&circuit_with_config(&data, &compressed_data, config)).is_satisfied());
+
+    let single = profile::profile(CircuitConfig::default()).unwrap();
+    let pair = profile::profile(config).unwrap();
+    assert_eq!(single.table_rows, 1 << 8);
+    assert_eq!(pair.table_rows, 1 << 16);
+    assert_eq!(
+        pair.regions[0].lookups, 16,
+        "two compressed data hash bytes per lookup"
+    );
+
+    // The max-size arrays: one lookup row(and gate) per byte, or per two bytes.
+    let region = |profile: &profile::Profile, name: &str| {
+        profile
+            .regions
+            .iter()
+            .find(|cost| cost.region == name)
+            .unwrap()
+            .clone()
+    };
+    for (name, len) in [
+        ("data byte range check", config.max_data_size()),
+        (
+            "compressed data byte range check",
+            config.max_compressed_data_size(),
+        ),
+    ] {
+        let (single, pair) = (region(&single, name), region(&pair, name));
+        println!(
+            "{name}: {len} bytes, {} gates with one byte per row, {} with two",
+            single.gates, pair.gates
+        );
+        assert_eq!((single.lookups, single.gates), (len, len));
+        assert_eq!(
+            (pair.lookups, pair.gates),
+            (len.div_ceil(2), len.div_ceil(2))
+        );
+    }
+    assert!(pair.total.gates < single.total.gates);
+}
+
+// xorshift64, so the random tests are reproducible without a rand dependency.
+struct TestRng(u64);
+
+impl TestRng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn byte(&mut self) -> u8 {
+        self.next() as u8
+    }
+
+    // Random bytes with a random number of leading zeroes, so every tag is covered.
+    fn word<const N: usize>(&mut self) -> [u8; N] {
+        let mut word = [0u8; N];
+        for byte in word[self.below(N + 1)..].iter_mut() {
+            *byte = self.byte();
+        }
+        word
+    }
+}
+
+// The circuit and the native decoder implement the same format, check they don't drift apart:
+// the circuit must be satisfied exactly when `try_decompress` decodes the compressed data into `data`.
+#[test]
+fn test_circuit_matches_native_decompress() {
+    let config = CircuitConfig {
+        capacity: 2,
+        ..Default::default()
+    };
+    let mut rng = TestRng(0x5eed_c0de);
+
+    for case in 0..24 {
+        let transitions: Vec<StorageTransition> = (0..config.capacity)
+            .map(|_| StorageTransition {
+                address: rng.word(),
+                key: rng.word(),
+                value: rng.word(),
+            })
+            .collect();
+        let data = StorageTransition::into_bytes(transitions.clone());
+        let mut compressed_data = StorageTransition::compress(transitions);
+
+        // a quarter of the cases is honest, the rest is corrupted in one byte
+        match case % 4 {
+            0 => {}
+            1 => {
+                let index = rng.below(compressed_data.len());
+                compressed_data[index] = rng.byte();
+            }
+            2 => {
+                compressed_data.remove(rng.below(compressed_data.len()));
+            }
+            _ => {
+                let index = rng.below(compressed_data.len() + 1);
+                compressed_data.insert(index, rng.byte());
+            }
+        }
+        if compressed_data.len() > config.max_compressed_data_size() {
+            continue;
+        }
+
+        let native = StorageTransition::try_decompress_without_preimages(&compressed_data)
+            .is_some_and(|decoded| StorageTransition::into_bytes(decoded) == data);
+        let satisfied =
+            synthesize(&circuit_with_config(&data, &compressed_data, config)).is_satisfied();
+        assert_eq!(
+            satisfied, native,
+            "case {case}, compressed data {compressed_data:?}"
+        );
+    }
+
+    // A tag 2 key(a preimage and an offset) is decoded natively, but not by the circuit.
+    let mut preimage = [0; STORAGE_KEY_OR_VALUE_SIZE];
+    preimage[STORAGE_KEY_OR_VALUE_SIZE - 1] = 7;
+    let mut offset = [0; STORAGE_KEY_OR_VALUE_SIZE];
+    offset[STORAGE_KEY_OR_VALUE_SIZE - 1] = 1;
+    let transition = StorageTransition {
+        address: [1; ADDRESS_SIZE],
+        key: slot_from_preimage_and_offset(preimage, offset).unwrap(),
+        value: [3; STORAGE_KEY_OR_VALUE_SIZE],
+    };
+    let data = StorageTransition::into_bytes(vec![transition.clone()]);
+    let mut compressed_data = vec![1];
+    compressed_data.extend(transition.address);
+    compressed_data.push(2);
+    compressed_data.extend(compress_leading_zeroes(preimage));
+    compressed_data.extend(compress_leading_zeroes(offset));
+    compressed_data.push(0);
+    compressed_data.extend(transition.value);
+
+    assert_eq!(
+        StorageTransition::try_decompress(&compressed_data),
+        Some(vec![transition])
+    );
+    assert_eq!(
+        StorageTransition::try_decompress_without_preimages(&compressed_data),
+        None
+    );
+    assert!(!synthesize(&circuit(&data, &compressed_data)).is_satisfied());
+}
+
+#[test]
+fn test_poseidon_transcript_proof() {
+    let transitions = transitions();
+    let config = CircuitConfig::default();
+    let setup = setup(config, &CrsSource::InsecureDev).unwrap();
+    let public_inputs = CompressionWitness::from_transitions(config, &transitions)
+        .unwrap()
+        .public_inputs::<Bn256>(config);
+
+    let proof = prove_with_transcript::<PoseidonTranscript<Bn256>>(&setup, &transitions).unwrap();
+    assert!(
+        verify_with_transcript::<PoseidonTranscript<Bn256>>(&setup.vk, &proof, &public_inputs)
+            .unwrap()
+    );
+    // the challenges are different, so the keccak verifier rejects it
+    assert!(!verify(&setup.vk, &proof, &public_inputs).unwrap());
+}
+
+#[test]
+fn test_poseidon_transcript_separates_bytes() {
+    let challenge = |chunks: &[&[u8]]| {
+        let mut transcript = PoseidonTranscript::<Bn256>::new();
+        for chunk in chunks {
+            transcript.commit_bytes(chunk);
+        }
+        transcript.get_challenge()
+    };
+
+    assert_ne!(challenge(&[&[0, 1]]), challenge(&[&[1]]));
+    assert_ne!(challenge(&[&[1, 2]]), challenge(&[&[1], &[2]]));
+}
+
+#[test]
+fn test_batch_verification_reports_invalid_proofs() {
+    let transitions = transitions();
+    let config = CircuitConfig::default();
+    let setup = setup(config, &CrsSource::InsecureDev).unwrap();
+    let proof = prove(&setup, &transitions).unwrap();
+    let public_inputs = CompressionWitness::from_transitions(config, &transitions)
+        .unwrap()
+        .public_inputs::<Bn256>(config);
+
+    let mut forged = proof.clone();
+    forged.opening_proof_at_z = G1Affine::one();
+    let mut wrong_inputs = public_inputs.clone();
+    wrong_inputs[0].add_assign(&Fr::one());
+
+    let batch = [
+        (&proof, public_inputs.as_slice()),
+        (&proof, public_inputs.as_slice()),
+    ];
+    assert_eq!(
+        verify_batch(&setup.vk, &batch).unwrap(),
+        Vec::<usize>::new()
+    );
+
+    let batch = [
+        (&proof, public_inputs.as_slice()),
+        (&forged, public_inputs.as_slice()),
+        (&proof, wrong_inputs.as_slice()),
+        (&proof, public_inputs.as_slice()),
+    ];
+    assert_eq!(verify_batch(&setup.vk, &batch).unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn test_packed_public_inputs() {
+    let transitions = transitions();
+    let data = StorageTransition::into_bytes(transitions.clone());
+    let compressed_data = StorageTransition::compress(transitions);
+    let config = CircuitConfig {
+        public_input: PublicInputMode::Packed,
+        ..Default::default()
+    };
+
+    let assembly = synthesize(&circuit_with_config(&data, &compressed_data, config));
+    assert!(assembly.is_satisfied());
+    let inputs = crate::utils::packed_public_inputs::<Bn256>(
+        &sha3::Keccak256::digest(&compressed_data),
+        &sha3::Keccak256::digest(&data),
+    );
+    assert_eq!(inputs.len(), 3);
+    assert_eq!(assembly.input_assingments, inputs);
+}
+
+#[test]
+fn test_state_tree_update() {
+    let transitions = transitions();
+    let mut tree = StateTree::<Bn256>::new();
+    let empty_root = tree.root();
+
+    let witness = tree.apply(&transitions).unwrap();
+    // the path is the whole slot hash, not its low bits
+    let index = crate::state::slot_index::<Bn256>(&transitions[0].address, &transitions[0].key);
+    assert!(index.bits() > 64);
+    assert_eq!(
+        witness.slots[0].siblings.len(),
+        crate::state::STATE_TREE_DEPTH
+    );
+    assert_eq!(
+        witness.old_root,
+        crate::utils::fe_to_bytes::<Bn256>(&empty_root)
+    );
+    assert_ne!(tree.root(), empty_root);
+    assert_eq!(witness.slots[0].old_value, [0; 32]);
+    assert_eq!(
+        tree.get(&transitions[0].address, &transitions[0].key),
+        transitions[0].value
+    );
+
+    // a zero value empties the slot
+    let mut deleted = transitions[0].clone();
+    deleted.value = [0; 32];
+    let witness = tree.apply(&[deleted]).unwrap();
+    assert_eq!(witness.slots[0].old_value, transitions[0].value);
+    assert_eq!(tree.root(), empty_root);
+}
+
+#[test]
+fn test_state_update_is_proven() {
+    let transitions = transitions();
+    let config = CircuitConfig {
+        state_update: true,
+        ..Default::default()
+    };
+    let mut tree = StateTree::<Bn256>::new();
+    let witness = CompressionWitness::from_transitions(config, &transitions)
+        .unwrap()
+        .with_state_update(&mut tree)
+        .unwrap();
+
+    let assembly = synthesize(&CompressionCircuit::new(config, &witness));
+    assert!(assembly.is_satisfied());
+    assert_eq!(
+        assembly.input_assingments,
+        witness.public_inputs::<Bn256>(config)
+    );
+    assert_eq!(
+        assembly.input_assingments[64..],
+        [StateTree::<Bn256>::new().root(), tree.root()]
+    );
+
+    // the slot was empty, so any other old value doesn't match the old root
+    let mut circuit = CompressionCircuit::new(config, &witness);
+    circuit.state.as_mut().unwrap().slots[0].old_value[31] = 1;
+    assert!(!synthesize(&circuit).is_satisfied());
+}
+
+#[test]
+fn test_blob_evaluation() {
+    // a constant polynomial over the whole blob
+    let mut element = [0u8; blob::BYTES_PER_FIELD_ELEMENT];
+    element[30] = 42;
+    let data = element.repeat(blob::FIELD_ELEMENTS_PER_BLOB);
+    let point = blob_evaluation_point(&[7; 32], &[1; 32]);
+    let mut expected = [0u8; 32];
+    expected[31] = 42;
+    assert_eq!(evaluate_blob(&data, &point), expected);
+
+    // the first element of the domain is 1
+    let mut one = [0u8; 32];
+    one[31] = 1;
+    assert_eq!(evaluate_blob(&element, &one), expected);
+}
+
+#[test]
+fn test_blob_evaluation_is_proven() {
+    let transitions = transitions();
+    let config = CircuitConfig {
+        commitment: CommitmentScheme::Poseidon,
+        blob_evaluation: true,
+        ..Default::default()
+    };
+    let witness = CompressionWitness::from_transitions(config, &transitions).unwrap();
+    let point = blob_evaluation_point(&[7; 32], &witness.compressed_data_hash::<Bn256>(config));
+    let witness = witness.with_blob_point(point);
+
+    let assembly = synthesize(&CompressionCircuit::new(config, &witness));
+    assert!(assembly.is_satisfied());
+    let inputs = witness.public_inputs::<Bn256>(config);
+    assert_eq!(assembly.input_assingments, inputs);
+    assert_eq!(
+        inputs[64..],
+        blob::blob_public_inputs::<Bn256>(&witness.compressed_data, &point)
+    );
+
+    // the value is the one of the point
+    let mut circuit = CompressionCircuit::new(config, &witness);
+    circuit.blob_point.as_mut().unwrap()[31] ^= 1;
+    let assembly = synthesize(&circuit);
+    assert!(assembly.is_satisfied());
+    assert_ne!(assembly.input_assingments[66..], inputs[66..]);
+}
+
+#[test]
+fn test_blob_evaluation_needs_poseidon_commitment() {
+    let config = CircuitConfig {
+        blob_evaluation: true,
+        ..Default::default()
+    };
+    let circuit = CompressionCircuit::<Bn256>::empty(config);
+    assert!(circuit.synthesize(&mut TestAssembly::new()).is_err());
+}
+
+// Bytes after the compressed data change the blob, but not the decompressed data.
+#[test]
+fn test_blob_trailing_bytes_are_rejected() {
+    let config = CircuitConfig {
+        commitment: CommitmentScheme::Poseidon,
+        blob_evaluation: true,
+        ..Default::default()
+    };
+    let witness = CompressionWitness::from_transitions(config, &transitions()).unwrap();
+    let point = blob_evaluation_point(&[7; 32], &witness.compressed_data_hash::<Bn256>(config));
+    let witness = witness.with_blob_point(point);
+
+    let mut circuit = CompressionCircuit::new(config, &witness);
+    circuit.compressed_data.push(Some(1));
+    let gate = debug::check(&circuit)
+        .unwrap()
+        .expect("trailing bytes must fail");
+    assert_eq!(gate.region, "blob evaluation");
+}
+
+// y + q is congruent to the value, only its range rejects it.
+#[test]
+fn test_blob_value_must_be_reduced() {
+    let value = blob::modulus() - 1u32;
+    for (value, satisfied) in [(value.clone(), true), (value + blob::modulus(), false)] {
+        let mut assembly = TestAssembly::new();
+        let columns = vec![
+            PolyIdentifier::VariablesPolynomial(0),
+            PolyIdentifier::VariablesPolynomial(1),
+            PolyIdentifier::VariablesPolynomial(2),
+        ];
+        let table = LookupTableApplication::new_range_table_of_width_3(8, columns).unwrap();
+        let table_name = table.functional_name();
+        assembly.add_table(table).unwrap();
+
+        blob::Element::alloc_reduced(&mut assembly, Some(value), &table_name).unwrap();
+        assert_eq!(assembly.is_satisfied(), satisfied);
+    }
+}
+
+#[test]
+fn test_canonical_order_is_proven() {
+    let config = CircuitConfig {
+        capacity: 2,
+        canonical_order: true,
+        ..Default::default()
+    };
+    let mut transitions = transitions();
+    let mut next = transitions[0].clone();
+    next.key[31] += 1;
+    transitions.push(next);
+
+    let witness = CompressionWitness::from_transitions(config, &transitions).unwrap();
+    assert!(synthesize(&CompressionCircuit::new(config, &witness)).is_satisfied());
+
+    let mut repeated = transitions.clone();
+    repeated[1].key = repeated[0].key;
+    let mut swapped = transitions.clone();
+    swapped.swap(0, 1);
+    let unordered = CircuitConfig {
+        canonical_order: false,
+        ..config
+    };
+    for transitions in [repeated, swapped] {
+        assert_eq!(
+            CompressionWitness::from_transitions(config, &transitions),
+            Err(WitnessError::NotCanonical { index: 1 })
+        );
+        let witness = CompressionWitness::from_transitions(unordered, &transitions).unwrap();
+        assert!(!synthesize(&CompressionCircuit::new(config, &witness)).is_satisfied());
+    }
+}
+
+#[test]
+fn test_address_back_reference_is_proven() {
+    let config = CircuitConfig {
+        capacity: 2,
+        ..Default::default()
+    };
+    let mut transitions = transitions();
+    let mut next = transitions[0].clone();
+    next.key[31] += 1;
+    transitions.push(next);
+
+    let data = StorageTransition::into_bytes(transitions.clone());
+    let second = StorageTransition::compress(transitions[..1].to_vec()).len();
+    let compressed_data = StorageTransition::compress(transitions);
+    assert_eq!(compressed_data[second], 3);
+    assert!(synthesize(&circuit_with_config(&data, &compressed_data, config)).is_satisfied());
+
+    // the address of the previous transition is not the address of the first one
+    let mut other_address = data.clone();
+    other_address[crate::config::TRANSITION_SIZE] ^= 1;
+    assert!(!synthesize(&circuit_with_config(
+        &other_address,
+        &compressed_data,
+        config
+    ))
+    .is_satisfied());
+
+    // the first transition has no previous address
+    let mut first = vec![3];
+    first.extend(&compressed_data[1 + ADDRESS_SIZE..second]);
+    first.extend(&compressed_data[second..]);
+    let unsatisfied = debug::check(&circuit_with_config(&data, &first, config))
+        .unwrap()
+        .unwrap();
+    assert_eq!(unsatisfied.region, "word 0 address check");
+}
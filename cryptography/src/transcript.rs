@@ -0,0 +1,78 @@
+use franklin_crypto::bellman::{
+    plonk::commitments::transcript::{Prng, Transcript},
+    Engine, Field, PrimeField, PrimeFieldRepr,
+};
+
+use crate::utils::{pack_bytes_to_field_elements, poseidon_hash};
+
+// Algebraic Fiat-Shamir transcript over the Poseidon sponge of `utils::poseidon_hash`.
+//
+// `RollingKeccakTranscript` is cheap natively, but keccak costs tens of thousands of gates
+// in-circuit. A proof which is verified recursively should be made with this one instead.
+// Every challenge is the hash of the previous challenge and the elements committed since,
+// so a verifier circuit can replay it with `main_circuit::circuit_poseidon_hash`.
+// Elements of other fields(coordinates of the commitments) are committed as 128-bit limbs.
+#[derive(Clone)]
+pub struct PoseidonTranscript<E: Engine> {
+    state: E::Fr,
+    pending: Vec<E::Fr>,
+}
+
+impl<E: Engine> Prng<E::Fr> for PoseidonTranscript<E> {
+    type Input = E::Fr;
+    type InitializationParameters = ();
+
+    fn new() -> Self {
+        Self {
+            state: E::Fr::zero(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn commit_input(&mut self, input: &Self::Input) {
+        self.pending.push(*input);
+    }
+
+    fn get_challenge(&mut self) -> E::Fr {
+        let mut input = Vec::with_capacity(1 + self.pending.len());
+        input.push(self.state);
+        input.append(&mut self.pending);
+        self.state = poseidon_hash::<E>(&input);
+
+        self.state
+    }
+}
+
+impl<E: Engine> Transcript<E::Fr> for PoseidonTranscript<E> {
+    // The length goes first, otherwise different bytes pack into the same elements,
+    // e.g. [0, 1] and [1], or one slice and the same bytes committed in two calls.
+    fn commit_bytes(&mut self, bytes: &[u8]) {
+        self.pending
+            .push(E::Fr::from_str(&format!("{}", bytes.len())).unwrap());
+        self.pending
+            .extend(pack_bytes_to_field_elements::<E>(bytes));
+    }
+
+    fn commit_field_element(&mut self, element: &E::Fr) {
+        self.pending.push(*element);
+    }
+
+    fn get_challenge_bytes(&mut self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32);
+        self.get_challenge()
+            .into_repr()
+            .write_be(&mut bytes)
+            .expect("field element fits into 32 bytes");
+        bytes
+    }
+
+    fn commit_fe<FF: PrimeField>(&mut self, element: &FF) {
+        let repr = element.into_repr();
+        for limbs in repr.as_ref().chunks(2) {
+            let mut packed = <E::Fr as PrimeField>::Repr::default();
+            packed.as_mut()[..limbs.len()].copy_from_slice(limbs);
+            self.pending
+                .push(E::Fr::from_repr(packed).expect("128 bits fit into the field"));
+        }
+    }
+}
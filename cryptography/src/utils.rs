@@ -1,8 +1,9 @@
 pub use compression::utils::{ADDRESS_SIZE, STORAGE_KEY_OR_VALUE_SIZE};
+use compression::{sha3, sha3::Digest};
+use franklin_crypto::bellman::{Engine, Field, PrimeField, PrimeFieldRepr};
+use rescue_poseidon::{GenericSponge, PoseidonParams};
 
-pub const MAX_COMPRESSED_DATA_SIZE: usize = 132; // 33 * 3 + 33
-pub const MAX_UNCOMPRESSED_DATA_SIZE: usize = 129; // 32 * 3 + 33
-pub const MAX_WORDS: usize = 3;
+use crate::config::{CircuitConfig, CommitmentScheme};
 
 pub fn get_word_position_in_sequence_of_data(mut index: usize) -> (usize, usize) {
     let k = index / 3;
@@ -25,3 +26,103 @@ pub fn get_word_position_in_sequence_of_data(mut index: usize) -> (usize, usize)
     };
     (offset, size)
 }
+
+// Pack bytes into field elements, 31 bytes per element in big-endian order.
+// Native counterpart of `main_circuit::pack_bytes`.
+pub fn pack_bytes_to_field_elements<E: Engine>(bytes: &[u8]) -> Vec<E::Fr> {
+    let shift = E::Fr::from_str("256").unwrap();
+    bytes
+        .chunks(31)
+        .map(|chunk| {
+            let mut result = E::Fr::zero();
+            for byte in chunk {
+                result.mul_assign(&shift);
+                result.add_assign(&E::Fr::from_str(&format!("{}", byte)).unwrap());
+            }
+            result
+        })
+        .collect()
+}
+
+// Field element as 32 big-endian bytes.
+pub fn fe_to_bytes<E: Engine>(value: &E::Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value
+        .into_repr()
+        .write_be(&mut bytes[..])
+        .expect("field element fits into 32 bytes");
+    bytes
+}
+
+pub fn fe_to_le_bytes<E: Engine>(value: &E::Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value
+        .into_repr()
+        .write_le(&mut bytes[..])
+        .expect("field element fits into 32 bytes");
+    bytes
+}
+
+// Inverse of `fe_to_bytes`, panics on a non-canonical encoding.
+pub fn fe_from_bytes<E: Engine>(bytes: &[u8; 32]) -> E::Fr {
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.read_be(&bytes[..]).expect("32 bytes");
+    E::Fr::from_repr(repr).expect("canonical field element")
+}
+
+// Poseidon hash of field elements. Native counterpart of `main_circuit::circuit_poseidon_hash`.
+pub fn poseidon_hash<E: Engine>(input: &[E::Fr]) -> E::Fr {
+    let params = PoseidonParams::<E, 2, 3>::default();
+    GenericSponge::hash(input, &params, None)[0]
+}
+
+// Commitment to the compressed data, the native counterpart of `compressed_data_hash` in the circuit.
+pub fn compressed_data_commitment<E: Engine>(
+    config: CircuitConfig,
+    compressed_data: &[u8],
+) -> Vec<u8> {
+    match config.commitment {
+        CommitmentScheme::Keccak => sha3::Keccak256::digest(compressed_data).to_vec(),
+        CommitmentScheme::Poseidon => {
+            assert!(compressed_data.len() <= config.max_compressed_data_size());
+            let mut padded = compressed_data.to_vec();
+            padded.resize(config.max_compressed_data_size(), 0);
+
+            let mut input = pack_bytes_to_field_elements::<E>(&padded);
+            input.push(E::Fr::from_str(&format!("{}", compressed_data.len())).unwrap());
+
+            let mut result = Vec::with_capacity(32);
+            poseidon_hash::<E>(&input)
+                .into_repr()
+                .write_be(&mut result)
+                .unwrap();
+            result
+        }
+    }
+}
+
+// Public inputs of the circuit in `PublicInputMode::Packed` mode.
+pub fn packed_public_inputs<E: Engine>(
+    compressed_data_hash: &[u8],
+    data_hash: &[u8],
+) -> Vec<E::Fr> {
+    pack_bytes_to_field_elements::<E>(&[compressed_data_hash, data_hash].concat())
+}
+
+// The only public input of the circuit in `PublicInputMode::Commitment` mode.
+// Any new public metadata must be appended here and in the circuit in the same order.
+// `extra_inputs` are the inputs of the optional sections: the old and the new state root with
+// `CircuitConfig::state_update`, then the blob inputs with `CircuitConfig::blob_evaluation`.
+pub fn public_input_commitment<E: Engine>(
+    compressed_data_hash: &[u8],
+    data_hash: &[u8],
+    compressed_data_len: usize,
+    extra_inputs: &[E::Fr],
+) -> E::Fr {
+    let mut input = pack_bytes_to_field_elements::<E>(compressed_data_hash);
+    input.extend(pack_bytes_to_field_elements::<E>(data_hash));
+    input.push(E::Fr::from_str(&format!("{}", compressed_data_len)).unwrap());
+    input.extend_from_slice(extra_inputs);
+
+    poseidon_hash::<E>(&input)
+}
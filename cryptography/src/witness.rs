@@ -0,0 +1,224 @@
+use std::fmt;
+
+use compression::{sha3, sha3::Digest, StorageTransition};
+use franklin_crypto::bellman::{Engine, PrimeField};
+use num_bigint::BigUint;
+
+use crate::blob::blob_public_inputs;
+use crate::config::{CircuitConfig, PublicInputMode, TRANSITION_SIZE};
+use crate::state::{StateTree, StateWitness};
+use crate::utils::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessError {
+    // The batch has more transitions than the circuit capacity.
+    TooManyTransitions { transitions: usize, capacity: usize },
+    // The batch has fewer transitions than the circuit capacity. The circuit decodes every
+    // transition of the capacity, so the zero padding of the data can't be proven.
+    // The witness isn't padded with filler transitions either: they would be a part of
+    // `data_hash`, the state update and the canonical order, so only the caller can pick them.
+    PartialBatch { transitions: usize, capacity: usize },
+    // The compressed data doesn't fit into `CircuitConfig::max_compressed_data_size`.
+    CompressedDataTooLarge { len: usize, capacity: usize },
+    // The uncompressed data is not a sequence of whole transitions.
+    InvalidDataLength(usize),
+    // The state tree slot of the transition is taken by another key(a Poseidon collision),
+    // see `StateTree`.
+    SlotCollision { index: BigUint },
+    // The transition is not above the previous one by (address, key),
+    // see `CircuitConfig::canonical_order`.
+    NotCanonical { index: usize },
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::TooManyTransitions {
+                transitions,
+                capacity,
+            } => write!(
+                f,
+                "batch has {transitions} transitions, but the circuit capacity is {capacity}"
+            ),
+            WitnessError::PartialBatch {
+                transitions,
+                capacity,
+            } => write!(
+                f,
+                "batch has {transitions} transitions, but the circuit proves exactly {capacity}"
+            ),
+            WitnessError::CompressedDataTooLarge { len, capacity } => write!(
+                f,
+                "compressed data is {len} bytes, but the circuit capacity is {capacity} bytes"
+            ),
+            WitnessError::InvalidDataLength(len) => write!(
+                f,
+                "data length {len} is not a multiple of the transition size {TRANSITION_SIZE}"
+            ),
+            WitnessError::SlotCollision { index } => {
+                write!(f, "state tree slot {index} is taken by another key")
+            }
+            WitnessError::NotCanonical { index } => write!(
+                f,
+                "transition {index} is not above the previous one by (address, key)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WitnessError {}
+
+// Native values of the circuit witness, checked against the circuit capacity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionWitness {
+    pub data: Vec<u8>,
+    pub compressed_data: Vec<u8>,
+    pub data_hash: Vec<u8>,
+    // Only with `CircuitConfig::state_update`.
+    pub state: Option<StateWitness>,
+    // Only with `CircuitConfig::blob_evaluation`, 32 big-endian bytes.
+    pub blob_point: Option<[u8; 32]>,
+}
+
+impl CompressionWitness {
+    pub fn from_transitions(
+        config: CircuitConfig,
+        transitions: &[StorageTransition],
+    ) -> Result<Self, WitnessError> {
+        // Checked before the compression, so a huge batch fails fast.
+        check_transitions_count(config, transitions.len())?;
+
+        let data = StorageTransition::into_bytes(transitions.to_vec());
+        let compressed_data = StorageTransition::compress(transitions.to_vec());
+
+        Self::from_bytes(config, &data, &compressed_data)
+    }
+
+    // Build the witness from already serialized data.
+    // The compressed data is not checked to decompress into the data, that is what the circuit proves.
+    pub fn from_bytes(
+        config: CircuitConfig,
+        data: &[u8],
+        compressed_data: &[u8],
+    ) -> Result<Self, WitnessError> {
+        if data.len() % TRANSITION_SIZE != 0 {
+            return Err(WitnessError::InvalidDataLength(data.len()));
+        }
+        check_transitions_count(config, data.len() / TRANSITION_SIZE)?;
+        if compressed_data.len() > config.max_compressed_data_size() {
+            return Err(WitnessError::CompressedDataTooLarge {
+                len: compressed_data.len(),
+                capacity: config.max_compressed_data_size(),
+            });
+        }
+        if config.canonical_order {
+            if let Some(index) = parse_transitions(data)
+                .windows(2)
+                .position(|pair| !StorageTransition::is_canonical(pair))
+            {
+                return Err(WitnessError::NotCanonical { index: index + 1 });
+            }
+        }
+
+        Ok(Self {
+            data: data.to_vec(),
+            compressed_data: compressed_data.to_vec(),
+            data_hash: sha3::Keccak256::digest(data).to_vec(),
+            state: None,
+            blob_point: None,
+        })
+    }
+
+    // Apply the transitions of the witness to the tree and add the witness of the state update.
+    pub fn with_state_update<E: Engine>(
+        mut self,
+        tree: &mut StateTree<E>,
+    ) -> Result<Self, WitnessError> {
+        self.state = Some(tree.apply(&parse_transitions(&self.data))?);
+
+        Ok(self)
+    }
+
+    // Evaluate the blob of the compressed data at the point, see `blob::blob_evaluation_point`.
+    pub fn with_blob_point(mut self, point: [u8; 32]) -> Self {
+        self.blob_point = Some(point);
+        self
+    }
+
+    pub fn compressed_data_hash<E: Engine>(&self, config: CircuitConfig) -> Vec<u8> {
+        compressed_data_commitment::<E>(config, &self.compressed_data)
+    }
+
+    pub fn compressed_data_len<E: Engine>(&self) -> E::Fr {
+        E::Fr::from_str(&format!("{}", self.compressed_data.len())).unwrap()
+    }
+
+    // Public inputs of the proof for this witness, computed natively.
+    // The state roots and the blob inputs follow the inputs of the mode,
+    // or are a part of the commitment.
+    pub fn public_inputs<E: Engine>(&self, config: CircuitConfig) -> Vec<E::Fr> {
+        let compressed_data_hash = self.compressed_data_hash::<E>(config);
+        let mut extra_inputs: Vec<E::Fr> = match &self.state {
+            Some(state) if config.state_update => vec![
+                fe_from_bytes::<E>(&state.old_root),
+                fe_from_bytes::<E>(&state.new_root),
+            ],
+            _ => vec![],
+        };
+        if let Some(point) = self.blob_point.as_ref().filter(|_| config.blob_evaluation) {
+            extra_inputs.extend(blob_public_inputs::<E>(&self.compressed_data, point));
+        }
+
+        let mut inputs = match config.public_input {
+            PublicInputMode::Bytes => compressed_data_hash
+                .iter()
+                .chain(self.data_hash.iter())
+                .map(|byte| E::Fr::from_str(&format!("{}", byte)).unwrap())
+                .collect(),
+            PublicInputMode::Commitment => {
+                return vec![public_input_commitment::<E>(
+                    &compressed_data_hash,
+                    &self.data_hash,
+                    self.compressed_data.len(),
+                    &extra_inputs,
+                )]
+            }
+            PublicInputMode::Packed => {
+                packed_public_inputs::<E>(&compressed_data_hash, &self.data_hash)
+            }
+        };
+        inputs.extend(extra_inputs);
+        inputs
+    }
+}
+
+// Inverse of `StorageTransition::into_bytes`, the data is a sequence of whole transitions.
+fn parse_transitions(data: &[u8]) -> Vec<StorageTransition> {
+    data.chunks(TRANSITION_SIZE)
+        .map(|transition| {
+            let (address, rest) = transition.split_at(ADDRESS_SIZE);
+            let (key, value) = rest.split_at(STORAGE_KEY_OR_VALUE_SIZE);
+            StorageTransition {
+                address: address.try_into().unwrap(),
+                key: key.try_into().unwrap(),
+                value: value.try_into().unwrap(),
+            }
+        })
+        .collect()
+}
+
+fn check_transitions_count(config: CircuitConfig, transitions: usize) -> Result<(), WitnessError> {
+    if transitions > config.capacity {
+        return Err(WitnessError::TooManyTransitions {
+            transitions,
+            capacity: config.capacity,
+        });
+    }
+    if transitions < config.capacity {
+        return Err(WitnessError::PartialBatch {
+            transitions,
+            capacity: config.capacity,
+        });
+    }
+    Ok(())
+}